@@ -0,0 +1,113 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput, black_box};
+
+use ddave::{
+    batch::Batch,
+    dave::Dave,
+    game::*,
+    input::{Input, Buttons},
+    level::*,
+};
+
+// Like the game itself, these need the levels extracted by level_ext.
+
+const BATCH_SIZE: usize = 1024;
+
+// Something that keeps Dave moving around the level, so the updates aren't just
+// him standing still.
+fn policy(index: usize, game: &Game) -> Buttons {
+    const ACTIONS: [Buttons; 4] = [
+        Buttons::RIGHT,
+        Buttons::RIGHT,
+        Buttons::JUMP,
+        Buttons::LEFT,
+    ];
+
+    let x = game.dave().pixel_position.x as usize;
+    ACTIONS[(index + x / 16) % ACTIONS.len()]
+}
+
+fn bench_game_update(c: &mut Criterion) {
+    let levels = load_levels().expect("Failed to load levels");
+    let level = LevelId::new(2).unwrap();
+
+    let mut group = c.benchmark_group("game");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("update", |b| {
+        let mut game = Game::init_level(level, &levels);
+        b.iter(|| {
+            if game.quit() {
+                game = Game::init_level(level, &levels);
+            }
+
+            game.input(&Input::from_buttons(policy(0, &game)));
+            game.update(&levels);
+            game.drain_sounds();
+        })
+    });
+    group.finish();
+}
+
+fn bench_batch(c: &mut Criterion) {
+    let level = LevelId::new(2).unwrap();
+
+    let mut batch = Batch::new(load_levels().expect("Failed to load levels"), level, BATCH_SIZE);
+
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+    group.bench_function("step", |b| {
+        b.iter(|| {
+            for i in 0..BATCH_SIZE {
+                if batch.games()[i].quit() {
+                    batch.reset(i, level);
+                }
+            }
+
+            batch.step(policy);
+        });
+    });
+    group.finish();
+
+    let stats = batch.stats();
+    println!("batch: {} steps, {:.0} steps per second", stats.steps, stats.steps_per_second());
+}
+
+fn bench_collision(c: &mut Criterion) {
+    let levels = load_levels().expect("Failed to load levels");
+    let tiles = levels[LevelId::new(2).unwrap()].tiles();
+
+    // Every pixel of the level.
+    let positions: Vec<_> = (0..160i16)
+        .flat_map(|y| (0..1600i16).map(move |x| Position { x, y }))
+        .collect();
+
+    let mut group = c.benchmark_group("collision");
+    group.throughput(Throughput::Elements(positions.len() as u64));
+    group.bench_function("is_clear", |b| {
+        b.iter(|| {
+            for &pos in &positions {
+                black_box(is_clear(tiles, pos));
+            }
+        })
+    });
+
+    // Every tile position, as that's all Dave can stand on.
+    let positions: Vec<_> = positions.into_iter()
+        .filter(|p| p.x % 16 == 0 && p.y % 16 == 0)
+        .collect();
+
+    group.throughput(Throughput::Elements(positions.len() as u64));
+    group.bench_function("check_collision", |b| {
+        b.iter(|| {
+            for &pos in &positions {
+                let mut dave = Dave::init();
+                dave.pixel_position = pos;
+                dave.check_collision(tiles);
+                black_box(&dave);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_game_update, bench_batch, bench_collision);
+criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    str::FromStr,
+};
+
+use crate::{
+    Result, TILE_SIZE,
+    game::{Game, Position},
+    renderer::{Renderer, RenderBackend},
+    tileset::{TileImages, TilePack},
+};
+
+// Ways to tell the tiles apart for players who can't rely on their colours, which
+// are all that sets the hazards, the pickups and the door apart in the original.
+//
+// The palettes shift the colours that someone with one of the common colour vision
+// deficiencies would see as the same towards ones they can tell apart. It's the usual
+// daltonize approach: work out what the colour looks like to them, and move what's
+// been lost into the channels they can see. The tiles are recoloured once, when
+// they're loaded, so it applies to everything drawn from them.
+//
+// High contrast mode outlines the tiles that matter, with a different shape and
+// colour for each kind (from the Okabe-Ito palette, which works for all three
+// deficiencies):
+//
+//  Hazards  A solid outline, in orange.
+//  Pickups  Corner brackets, in yellow.
+//  Doors    A double outline, in sky blue.
+
+const HAZARD_COLOR: [f32; 4] = [0.84, 0.37, 0.0, 1.0];
+const PICKUP_COLOR: [f32; 4] = [0.94, 0.89, 0.26, 1.0];
+const DOOR_COLOR: [f32; 4] = [0.34, 0.71, 0.91, 1.0];
+
+// How far the corner brackets reach along each edge.
+const BRACKET_LENGTH: f64 = 5.0;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Palette {
+    #[default]
+    Original,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl FromStr for Palette {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Palette> {
+        match s.to_ascii_lowercase().as_str() {
+            "original" => Ok(Palette::Original),
+            "protanopia" => Ok(Palette::Protanopia),
+            "deuteranopia" => Ok(Palette::Deuteranopia),
+            "tritanopia" => Ok(Palette::Tritanopia),
+            _ => Err(format!("Unknown palette: {} (expected protanopia, deuteranopia or tritanopia)", s))?,
+        }
+    }
+}
+
+fn multiply(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+const RGB_TO_LMS: [[f32; 3]; 3] = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: [[f32; 3]; 3] = [
+    [0.08094445, -0.13050441, 0.11672107],
+    [-0.010248534, 0.05401933, -0.11361471],
+    [-0.00036529694, -0.0041216147, 0.6935114],
+];
+
+impl Palette {
+    // How the cones that are missing are made up from the other two.
+    fn simulation(self) -> Option<[[f32; 3]; 3]> {
+        match self {
+            Palette::Original => None,
+            Palette::Protanopia => Some([[0.0, 2.02344, -2.52581], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]),
+            Palette::Deuteranopia => Some([[1.0, 0.0, 0.0], [0.494207, 0.0, 1.24827], [0.0, 0.0, 1.0]]),
+            Palette::Tritanopia => Some([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [-0.395913, 0.801109, 0.0]]),
+        }
+    }
+
+    pub fn remap(self, color: [u8; 3]) -> [u8; 3] {
+        let simulation = match self.simulation() {
+            Some(simulation) => simulation,
+            None => return color,
+        };
+
+        let rgb = [color[0] as f32, color[1] as f32, color[2] as f32];
+        let seen = multiply(&LMS_TO_RGB, multiply(&simulation, multiply(&RGB_TO_LMS, rgb)));
+        let lost = [rgb[0] - seen[0], rgb[1] - seen[1], rgb[2] - seen[2]];
+
+        // What's lost goes into green and blue.
+        let shifted = [
+            rgb[0],
+            rgb[1] + 0.7 * lost[0] + lost[1],
+            rgb[2] + 0.7 * lost[0] + lost[2],
+        ];
+
+        [
+            shifted[0].round().clamp(0.0, 255.0) as u8,
+            shifted[1].round().clamp(0.0, 255.0) as u8,
+            shifted[2].round().clamp(0.0, 255.0) as u8,
+        ]
+    }
+
+    // The tiles only use a handful of colours, so each is only worked out once.
+    pub fn apply(self, tiles: &mut TileImages, pack: &mut TilePack) {
+        if self == Palette::Original {
+            return;
+        }
+
+        let mut colors = HashMap::new();
+        let mut remap = |color: [u8; 3]| *colors.entry(color).or_insert_with(|| self.remap(color));
+        tiles.recolor(&mut remap);
+        pack.recolor(&mut remap);
+    }
+}
+
+// Outlines the hazards, pickups and door on screen. Drawn a game pixel thick, so
+// they stay bold when the game is scaled up.
+pub fn draw_outlines<B: RenderBackend>(b: &mut B, game: &Game, view_x: f64, columns: Range<usize>) {
+    let tile = TILE_SIZE as f64;
+
+    for (i, &id) in game.tiles().iter().enumerate().filter(|(i, _)| columns.contains(&(i % 100))) {
+        let pos = Position {
+            x: (i % 100) as i16 * TILE_SIZE as i16,
+            y: (i / 100) as i16 * TILE_SIZE as i16,
+        };
+        let (x, y) = Renderer::to_screen(view_x, pos);
+
+        if id.is_hazard() {
+            outline(b, HAZARD_COLOR, [x, y, tile, tile]);
+        } else if id.is_pickup() {
+            brackets(b, PICKUP_COLOR, [x, y, tile, tile]);
+        } else if id.is_door() {
+            outline(b, DOOR_COLOR, [x, y, tile, tile]);
+            outline(b, DOOR_COLOR, [x + 2.0, y + 2.0, tile - 4.0, tile - 4.0]);
+        }
+    }
+}
+
+fn outline<B: RenderBackend>(b: &mut B, color: [f32; 4], rect: [f64; 4]) {
+    let [x, y, w, h] = rect;
+    b.fill_rect(color, [x, y, w, 1.0]);
+    b.fill_rect(color, [x, y + h - 1.0, w, 1.0]);
+    b.fill_rect(color, [x, y + 1.0, 1.0, h - 2.0]);
+    b.fill_rect(color, [x + w - 1.0, y + 1.0, 1.0, h - 2.0]);
+}
+
+fn brackets<B: RenderBackend>(b: &mut B, color: [f32; 4], rect: [f64; 4]) {
+    let [x, y, w, h] = rect;
+    let len = BRACKET_LENGTH;
+
+    for &(cx, cy) in &[(x, y), (x + w - len, y), (x, y + h - 1.0), (x + w - len, y + h - 1.0)] {
+        b.fill_rect(color, [cx, cy, len, 1.0]);
+    }
+    for &(cx, cy) in &[(x, y + 1.0), (x + w - 1.0, y + 1.0), (x, y + h - len), (x + w - 1.0, y + h - len)] {
+        b.fill_rect(color, [cx, cy, 1.0, len - 1.0]);
+    }
+}
\ No newline at end of file
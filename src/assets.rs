@@ -1,32 +1,146 @@
+use std::cell::{RefCell, Ref};
+
 use crate::{
     Result,
     tileset::*,
     level::*,
+    layer::StaticLayer,
+    accessibility::Palette,
 };
-use piston_window::{G2dTextureContext, G2dTexture};
+use image::RgbaImage;
+use piston_window::{G2dTextureContext, G2dTexture, GfxEncoder, Texture, TextureSettings, Filter, ImageSize};
 
 pub struct Assets {
     levels: Levels,
-    tiles: TileSet,
+    atlas: TileAtlas,
+    // Kept for drawing frames on the CPU, for screenshots and recordings.
+    tile_images: TileImages,
+    // Only the window draws the pack's tiles. The CPU sticks to the originals.
+    tile_pack: TilePack,
+    // Drawing only borrows the assets, so the textures are updated through this.
+    textures: RefCell<TextureCache>,
+}
+
+// One layer for the game being played, and one for the death replay, so switching
+// between them doesn't redraw the level each time.
+const LAYER_SLOTS: usize = 2;
+
+// The textures that change as the game's drawn, along with the context that they're
+// made with.
+struct TextureCache {
+    context: G2dTextureContext,
+    // The static level layers (see layer.rs).
+    layers: Vec<LayerTexture>,
+    // Counts the layers drawn, to find the one that's gone longest without.
+    draws: u64,
+    // The last post-processed frame, which each new one is written over.
+    frame: Option<G2dTexture>,
+}
+
+// A layer, and the texture it's uploaded to.
+struct LayerTexture {
+    layer: StaticLayer,
+    texture: Option<G2dTexture>,
+    last_drawn: u64,
 }
 
 impl Assets {
-    pub fn init(context: G2dTextureContext) -> Result<Assets> {
+    pub fn init(mut context: G2dTextureContext, tile_pack: Option<&str>, palette: Palette) -> Result<Assets> {
+        let mut tile_images = load_tile_images()?;
+        let mut tile_pack = match tile_pack {
+            Some(dir) => TilePack::load(dir, &tile_images)?,
+            None => TilePack::default(),
+        };
+        palette.apply(&mut tile_images, &mut tile_pack);
+        let atlas = load_atlas(&mut context, &tile_images, &tile_pack)?;
+
         Ok(Assets {
             levels: load_levels()?,
-            tiles: load_tileset(context)?,
+            atlas,
+            textures: RefCell::new(TextureCache {
+                context,
+                layers: (0..LAYER_SLOTS)
+                    .map(|_| LayerTexture {
+                        layer: StaticLayer::new(tile_pack.scale()),
+                        texture: None,
+                        last_drawn: 0,
+                    })
+                    .collect(),
+                draws: 0,
+                frame: None,
+            }),
+            tile_images,
+            tile_pack,
         })
     }
 
-    pub fn get_level(&self, id: LevelId) -> &Level {
-        &self.levels[id]
+    // The texture of the static tiles for the given level tiles, and the stamp
+    // they have (see Game::tiles_stamp). It's only uploaded again when they've
+    // changed. A layer that's already been drawn from the same tiles is used as it
+    // is, and otherwise the one drawn longest ago is brought up to date.
+    pub fn static_layer(&self, stamp: u64, tiles: &[TileId]) -> Result<Ref<'_, G2dTexture>> {
+        let index = {
+            let mut cache = self.textures.borrow_mut();
+            let TextureCache { context, layers, draws, .. } = &mut *cache;
+
+            let index = layers.iter()
+                .position(|l| l.layer.stamp() == Some(stamp))
+                .or_else(|| layers.iter().enumerate().min_by_key(|(_, l)| l.last_drawn).map(|(i, _)| i))
+                .unwrap_or(0);
+
+            let LayerTexture { layer, texture, last_drawn } = &mut layers[index];
+            if layer.update(stamp, tiles, &self.tile_images, &self.tile_pack) || texture.is_none() {
+                let settings = TextureSettings::new().filter(Filter::Nearest);
+                *texture = Some(Texture::from_image(context, layer.image(), &settings)?);
+            }
+
+            *draws += 1;
+            *last_drawn = *draws;
+            index
+        };
+
+        Ok(Ref::map(self.textures.borrow(), |cache| cache.layers[index].texture.as_ref().unwrap()))
+    }
+
+    // A post-processed frame (see postprocess.rs), as a texture to draw. The same
+    // texture is written over every time, unless the frame's changed size, and
+    // it's kept blocky, like the tiles.
+    pub fn frame_texture(&self, frame: &RgbaImage) -> Result<Ref<'_, G2dTexture>> {
+        {
+            let mut cache = self.textures.borrow_mut();
+            let TextureCache { context, frame: texture, .. } = &mut *cache;
+
+            match texture {
+                Some(texture) if texture.get_size() == frame.dimensions() => texture.update(context, frame)?,
+                _ => {
+                    let settings = TextureSettings::new().filter(Filter::Nearest);
+                    *texture = Some(Texture::from_image(context, frame, &settings)?);
+                },
+            }
+        }
+
+        Ok(Ref::map(self.textures.borrow(), |cache| cache.frame.as_ref().unwrap()))
+    }
+
+    // Updates to the textures go through their context's own encoder, which has to
+    // be flushed to the device before the window's is, at the end of the draw.
+    pub fn flush_textures<F: FnOnce(&mut GfxEncoder)>(&self, flush: F) {
+        flush(&mut self.textures.borrow_mut().context.encoder);
+    }
+
+    pub fn levels(&self) -> &Levels {
+        &self.levels
+    }
+
+    pub fn atlas(&self) -> &TileAtlas {
+        &self.atlas
     }
 
-    pub fn get_level_mut(&mut self, id: LevelId) -> &mut Level {
-        &mut self.levels[id]
+    pub fn tile_images(&self) -> &TileImages {
+        &self.tile_images
     }
 
-    pub fn get_tile(&self, id: TileId) -> &G2dTexture {
-        &self.tiles[id]
+    pub fn tile_pack(&self) -> &TilePack {
+        &self.tile_pack
     }
 }
\ No newline at end of file
@@ -0,0 +1,415 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::Result;
+
+pub const SAMPLE_RATE: u32 = 22_050;
+
+// The PC speaker can only produce a single square wave at a time, so all of the
+// original sound effects are just a sequence of frequencies, each held for a short
+// time. We synthesize the same thing here instead of shipping sample data.
+const AMPLITUDE: i16 = 0x1800;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SoundEffect {
+    Jump,
+    Gem,
+    Trophy,
+    Gunfire,
+    MonsterDeath,
+    DaveDeath,
+    Jetpack,
+    Door,
+    ExtraLife,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 9] = [
+        SoundEffect::Jump,
+        SoundEffect::Gem,
+        SoundEffect::Trophy,
+        SoundEffect::Gunfire,
+        SoundEffect::MonsterDeath,
+        SoundEffect::DaveDeath,
+        SoundEffect::Jetpack,
+        SoundEffect::Door,
+        SoundEffect::ExtraLife,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundEffect::Jump           => "jump",
+            SoundEffect::Gem            => "gem",
+            SoundEffect::Trophy         => "trophy",
+            SoundEffect::Gunfire        => "gunfire",
+            SoundEffect::MonsterDeath   => "monster_death",
+            SoundEffect::DaveDeath      => "dave_death",
+            SoundEffect::Jetpack        => "jetpack",
+            SoundEffect::Door           => "door",
+            SoundEffect::ExtraLife      => "extra_life",
+        }
+    }
+
+    // There's only one speaker, so a new sound cuts off the current one. To stop
+    // the jetpack hum from drowning out everything else, a sound can only interrupt
+    // one with an equal or lower priority.
+    fn priority(self) -> u8 {
+        match self {
+            SoundEffect::Jetpack => 0,
+            SoundEffect::Jump | SoundEffect::Gunfire => 1,
+            SoundEffect::Gem | SoundEffect::MonsterDeath => 2,
+            SoundEffect::Trophy | SoundEffect::Door | SoundEffect::ExtraLife => 3,
+            SoundEffect::DaveDeath => 4,
+        }
+    }
+
+    fn index(self) -> usize {
+        SoundEffect::ALL.iter().position(|&s| s == self).unwrap()
+    }
+
+    // These are approximations of the original effects, built out of sweeps and
+    // arpeggios in the same style.
+    pub fn approximate_tones(self) -> Vec<Tone> {
+        match self {
+            SoundEffect::Jump => sweep(200, 800, 10, 10),
+            SoundEffect::Gem => notes(&[1000, 1200, 1500, 2000], 30),
+            SoundEffect::Trophy => notes(&[523, 659, 784, 1047, 0, 1047], 80),
+            SoundEffect::Gunfire => sweep(1800, 300, 8, 10),
+            SoundEffect::MonsterDeath => {
+                let mut tones = sweep(800, 100, 15, 10);
+                // Make every other step jump up slightly to get the crunchy sound.
+                for t in tones.iter_mut().skip(1).step_by(2) {
+                    t.frequency += 150;
+                }
+                tones
+            },
+            SoundEffect::DaveDeath => {
+                let mut tones = sweep(1000, 60, 45, 20);
+                for t in tones.iter_mut().skip(1).step_by(2) {
+                    t.frequency /= 2;
+                }
+                tones
+            },
+            SoundEffect::Jetpack => notes(&[90, 110, 90, 110], 15),
+            SoundEffect::Door => sweep(300, 1200, 10, 40),
+            SoundEffect::ExtraLife => notes(&[1047, 1319, 1568, 2093, 1047, 1319, 1568, 2093], 50),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Tone {
+    // A frequency of 0 is silence.
+    pub frequency: u16,
+    pub duration_ms: u16,
+}
+
+fn sweep(from: u16, to: u16, steps: u16, step_ms: u16) -> Vec<Tone> {
+    let (from, to) = (from as i32, to as i32);
+    (0..steps as i32)
+        .map(|i| Tone {
+            frequency: (from + (to - from) * i / (steps as i32 - 1).max(1)) as u16,
+            duration_ms: step_ms,
+        })
+        .collect()
+}
+
+fn notes(frequencies: &[u16], note_ms: u16) -> Vec<Tone> {
+    frequencies.iter()
+        .map(|&frequency| Tone { frequency, duration_ms: note_ms })
+        .collect()
+}
+
+pub fn synthesize(tones: &[Tone], sample_rate: u32) -> Vec<i16> {
+    let mut samples = Vec::new();
+    // Keeping the phase running across tones avoids clicks at every frequency change.
+    let mut phase = 0.0f64;
+
+    for tone in tones {
+        let count = sample_rate as u64 * tone.duration_ms as u64 / 1000;
+        let step = tone.frequency as f64 / sample_rate as f64;
+
+        for _ in 0..count {
+            let sample = if tone.frequency == 0 {
+                0
+            } else if phase < 0.5 {
+                AMPLITUDE
+            } else {
+                -AMPLITUDE
+            };
+
+            samples.push(sample);
+            phase = (phase + step).fract();
+        }
+    }
+
+    samples
+}
+
+pub fn write_wav<W: Write>(mut out: W, samples: &[i16], sample_rate: u32) -> Result<()> {
+    let data_len = samples.len() as u32 * 2;
+
+    out.write_all(b"RIFF")?;
+    out.write_u32::<LittleEndian>(36 + data_len)?;
+    out.write_all(b"WAVE")?;
+
+    // Mono, 16-bit PCM.
+    out.write_all(b"fmt ")?;
+    out.write_u32::<LittleEndian>(16)?;
+    out.write_u16::<LittleEndian>(1)?;
+    out.write_u16::<LittleEndian>(1)?;
+    out.write_u32::<LittleEndian>(sample_rate)?;
+    out.write_u32::<LittleEndian>(sample_rate * 2)?;
+    out.write_u16::<LittleEndian>(2)?;
+    out.write_u16::<LittleEndian>(16)?;
+
+    out.write_all(b"data")?;
+    out.write_u32::<LittleEndian>(data_len)?;
+    for &s in samples {
+        out.write_i16::<LittleEndian>(s)?;
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+// See the comment at the top of the file written by sfx_ext for the format.
+pub fn load_sound_file<P: AsRef<Path>>(path: P) -> Result<Vec<(String, Vec<Tone>)>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut sounds: Vec<(String, Vec<Tone>)> = Vec::new();
+
+    for (line_num, line) in file.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("sound"), Some(name), None) => sounds.push((name.to_owned(), Vec::new())),
+            (Some(frequency), Some(duration), None) => {
+                let tone = Tone {
+                    frequency: frequency.parse()?,
+                    duration_ms: duration.parse()?,
+                };
+
+                match sounds.last_mut() {
+                    Some((_, tones)) => tones.push(tone),
+                    None => Err(format!("Tone before the first sound on line {}", line_num + 1))?,
+                }
+            },
+            _ => Err(format!("Invalid sound data on line {}: {}", line_num + 1, line))?,
+        }
+    }
+
+    Ok(sounds)
+}
+
+// Holds the synthesized samples of every effect, so we don't have to rebuild them
+// every time one is played.
+#[derive(Debug)]
+pub struct SoundBank {
+    sample_rate: u32,
+    samples: Vec<Vec<i16>>,
+}
+
+impl SoundBank {
+    // Uses the sounds in the given file (see sfx_ext) where it has them, and falls
+    // back to the approximations for the rest. Without a file, it's all of them.
+    pub fn load(sample_rate: u32, sound_file: Option<&Path>) -> Result<SoundBank> {
+        let extracted = match sound_file {
+            Some(path) => load_sound_file(path)?,
+            None => Vec::new(),
+        };
+
+        let samples = SoundEffect::ALL.iter()
+            .map(|s| match extracted.iter().find(|(name, _)| name == s.name()) {
+                Some((_, tones)) => synthesize(tones, sample_rate),
+                None => synthesize(&s.approximate_tones(), sample_rate),
+            })
+            .collect();
+
+        Ok(SoundBank { sample_rate, samples })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn get(&self, effect: SoundEffect) -> &[i16] {
+        &self.samples[effect.index()]
+    }
+
+    // Renders every effect to `<dir>/<name>.wav`. This doesn't need a sound device,
+    // so it can be used to check the output on headless machines.
+    pub fn render_wavs<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        for &effect in SoundEffect::ALL.iter() {
+            let path = dir.as_ref().join(format!("{}.wav", effect.name()));
+            let file = BufWriter::new(File::create(path)?);
+            write_wav(file, self.get(effect), self.sample_rate)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait AudioBackend {
+    // Starts playing the samples, stopping anything currently playing.
+    fn play(&mut self, samples: &[i16], sample_rate: u32);
+}
+
+// Used when there's no sound device, or the game was built without audio support.
+#[derive(Debug, Default)]
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _: &[i16], _: u32) {}
+}
+
+#[cfg(feature = "audio")]
+pub struct RodioAudio {
+    device: rodio::Device,
+    sink: Option<rodio::Sink>,
+}
+
+#[cfg(feature = "audio")]
+impl RodioAudio {
+    pub fn new() -> Option<RodioAudio> {
+        rodio::default_output_device().map(|device| RodioAudio { device, sink: None })
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioBackend for RodioAudio {
+    fn play(&mut self, samples: &[i16], sample_rate: u32) {
+        // Dropping the old sink stops whatever it was playing.
+        let sink = rodio::Sink::new(&self.device);
+        sink.append(rodio::buffer::SamplesBuffer::new(1, sample_rate, samples.to_vec()));
+        self.sink = Some(sink);
+    }
+}
+
+pub struct Audio {
+    bank: SoundBank,
+    backend: Box<dyn AudioBackend>,
+    playing: Option<(SoundEffect, u32)>,
+}
+
+impl Audio {
+    pub fn init(sound_file: Option<&Path>) -> Result<Audio> {
+        Ok(Audio {
+            bank: SoundBank::load(SAMPLE_RATE, sound_file)?,
+            backend: default_backend(),
+            playing: None,
+        })
+    }
+
+    pub fn play(&mut self, effect: SoundEffect) {
+        if let Some((current, _)) = self.playing {
+            if current == effect || current.priority() > effect.priority() {
+                return;
+            }
+        }
+
+        let samples = self.bank.get(effect);
+        self.backend.play(samples, self.bank.sample_rate());
+
+        // Track how many game ticks the effect lasts, rounding up.
+        let ticks = (samples.len() as u32 * 30).div_ceil(self.bank.sample_rate());
+        self.playing = Some((effect, ticks));
+    }
+
+    // Called once per game update.
+    pub fn update(&mut self) {
+        if let Some((_, ticks)) = &mut self.playing {
+            *ticks = ticks.saturating_sub(1);
+            if *ticks == 0 {
+                self.playing = None;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+fn default_backend() -> Box<dyn AudioBackend> {
+    match RodioAudio::new() {
+        Some(backend) => Box::new(backend),
+        None => {
+            println!("No audio device found, sound is disabled.");
+            Box::new(NullAudio)
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+fn default_backend() -> Box<dyn AudioBackend> {
+    Box::new(NullAudio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use byteorder::ByteOrder;
+
+    fn tone(frequency: u16, duration_ms: u16) -> Tone {
+        Tone { frequency, duration_ms }
+    }
+
+    #[test]
+    fn synthesize_sample_count_follows_duration() {
+        assert_eq!(synthesize(&[tone(440, 100)], 22_050).len(), 2205);
+        // Each tone rounds down on its own.
+        assert_eq!(synthesize(&[tone(440, 100), tone(880, 250)], 22_050).len(), 2205 + 5512);
+        assert_eq!(synthesize(&[tone(440, 1000)], 8_000).len(), 8_000);
+        assert!(synthesize(&[], 22_050).is_empty());
+    }
+
+    #[test]
+    fn synthesize_square_wave() {
+        let samples = synthesize(&[tone(1000, 10)], 8_000);
+        assert!(samples.iter().all(|&s| s == AMPLITUDE || s == -AMPLITUDE));
+        // 8 samples a cycle, high for the first half.
+        assert_eq!(&samples[..8], &[AMPLITUDE, AMPLITUDE, AMPLITUDE, AMPLITUDE, -AMPLITUDE, -AMPLITUDE, -AMPLITUDE, -AMPLITUDE]);
+    }
+
+    #[test]
+    fn synthesize_frequency_zero_is_silence() {
+        let samples = synthesize(&[tone(0, 50)], 22_050);
+        assert_eq!(samples.len(), 1102);
+        assert!(samples.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn write_wav_header() {
+        let samples = [0, 1, -1, i16::MAX, i16::MIN];
+        let mut out = Vec::new();
+        write_wav(&mut out, &samples, 22_050).unwrap();
+
+        assert_eq!(out.len(), 44 + samples.len() * 2);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(LittleEndian::read_u32(&out[4..]), 36 + 10);
+        assert_eq!(&out[8..16], b"WAVEfmt ");
+        assert_eq!(LittleEndian::read_u32(&out[16..]), 16);
+        // PCM, mono.
+        assert_eq!(LittleEndian::read_u16(&out[20..]), 1);
+        assert_eq!(LittleEndian::read_u16(&out[22..]), 1);
+        assert_eq!(LittleEndian::read_u32(&out[24..]), 22_050);
+        assert_eq!(LittleEndian::read_u32(&out[28..]), 44_100);
+        // Block align and bits per sample.
+        assert_eq!(LittleEndian::read_u16(&out[32..]), 2);
+        assert_eq!(LittleEndian::read_u16(&out[34..]), 16);
+        assert_eq!(&out[36..40], b"data");
+        assert_eq!(LittleEndian::read_u32(&out[40..]), 10);
+
+        let mut written = [0; 5];
+        LittleEndian::read_i16_into(&out[44..], &mut written);
+        assert_eq!(written, samples);
+    }
+}
\ No newline at end of file
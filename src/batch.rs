@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::{
+    game::Game,
+    level::*,
+    input::{Input, Buttons},
+};
+
+// Runs a large number of independent games side by side, spreading the updates
+// over every core. The level data is never changed by a game, so one copy is
+// shared by all of them.
+pub struct Batch {
+    levels: Levels,
+    games: Vec<Game>,
+    steps: u64,
+    elapsed: Duration,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BatchStats {
+    // The number of single game updates, over all games.
+    pub steps: u64,
+    pub elapsed: Duration,
+}
+
+impl BatchStats {
+    pub fn steps_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.steps as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Batch {
+    pub fn new(levels: Levels, level: LevelId, count: usize) -> Batch {
+        let games = (0..count)
+            .map(|_| Game::init_level(level, &levels))
+            .collect();
+
+        Batch {
+            levels,
+            games,
+            steps: 0,
+            elapsed: Duration::default(),
+        }
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    pub fn reset(&mut self, index: usize, level: LevelId) {
+        self.games[index] = Game::init_level(level, &self.levels);
+    }
+
+    // Updates every game that hasn't finished, with the buttons chosen for it by
+    // the policy. The policy is given the game's index, so it can keep its own
+    // state for each one.
+    pub fn step<F>(&mut self, policy: F)
+        where F: Fn(usize, &Game) -> Buttons + Sync
+    {
+        let start = Instant::now();
+        let levels = &self.levels;
+
+        let steps: u64 = self.games.par_iter_mut()
+            .enumerate()
+            .filter(|(_, game)| !game.quit())
+            .map(|(i, game)| {
+                let input = Input::from_buttons(policy(i, game));
+                game.input(&input);
+                game.update(levels);
+                game.drain_sounds();
+                1
+            })
+            .sum();
+
+        self.steps += steps;
+        self.elapsed += start.elapsed();
+    }
+
+    // Like `step`, but with the buttons for each game given up front.
+    pub fn step_with(&mut self, actions: &[Buttons]) {
+        assert_eq!(actions.len(), self.games.len(), "Need one action per game");
+        self.step(|i, _| actions[i]);
+    }
+
+    pub fn stats(&self) -> BatchStats {
+        BatchStats {
+            steps: self.steps,
+            elapsed: self.elapsed,
+        }
+    }
+}
\ No newline at end of file
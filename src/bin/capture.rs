@@ -0,0 +1,105 @@
+use std::env;
+
+use ddave::{
+    Result,
+    game::{Game, CLASSIC_VIEW_WIDTH},
+    level::*,
+    input::Input,
+    replay::Replay,
+    renderer::Renderer,
+    tileset::load_tile_images,
+    capture::*,
+    postprocess::PostProcess,
+    camera::{CameraMode, SmoothCamera},
+};
+
+// Plays a replay offscreen, and records it without opening the game window.
+//
+// Usage: capture [--scale <n>] [--frame <update>] [--view-width <tiles>] [--filter <filters>] [--smooth-camera] [--camera-dead-zone <pixels>] [--camera-easing <amount>] <replay> <output>
+//
+// The output is an animated GIF if it ends in `.gif`, and a folder of numbered PNGs
+// otherwise. With `--frame`, only the frame after that many updates is saved, as a
+// single PNG. The view width has to match the one the replay was recorded with, as
+// it changes when monsters shoot. `--filter` post-processes the frames the same way
+// as the game's option, which doubles their size before `--scale`. Monsters only
+// shoot at Dave when he's on screen, so a replay recorded with the smooth camera has
+// to be played back with it too, and with the same settings.
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let mut scale = 1;
+    let mut frame = None;
+    let mut view_width = CLASSIC_VIEW_WIDTH;
+    let mut post_process = PostProcess::default();
+    let mut smooth_camera = false;
+    let mut smooth = SmoothCamera::default();
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scale" => scale = args.next().ok_or("--scale needs a scale")?.parse()?,
+            "--frame" => frame = Some(args.next().ok_or("--frame needs an update number")?.parse::<usize>()?),
+            "--view-width" => view_width = args.next().ok_or("--view-width needs a number of tiles")?.parse()?,
+            "--filter" => post_process = args.next().ok_or("--filter needs a list of filters")?.parse()?,
+            "--smooth-camera" => smooth_camera = true,
+            "--camera-dead-zone" => smooth.dead_zone = args.next().ok_or("--camera-dead-zone needs a number of pixels")?.parse()?,
+            "--camera-easing" => smooth.easing = args.next().ok_or("--camera-easing needs a number from 0 to 1")?.parse()?,
+            path => paths.push(path.to_owned()),
+        }
+    }
+
+    if paths.len() != 2 {
+        Err("Usage: capture [--scale <n>] [--frame <update>] [--view-width <tiles>] [--filter <filters>] [--smooth-camera] [--camera-dead-zone <pixels>] [--camera-easing <amount>] <replay> <output>")?;
+    }
+
+    let replay = Replay::load_file(&paths[0])?;
+    let output = &paths[1];
+
+    let levels = load_levels()?;
+    let tiles = load_tile_images()?;
+    let mut game = Game::init_level(replay.level, &levels).with_view_width(view_width);
+    let camera = if smooth_camera { CameraMode::Smooth(smooth) } else { CameraMode::Classic };
+    let mut renderer = Renderer::new().camera(camera);
+
+    let draw_frame = |renderer: &Renderer, game: &Game| {
+        let frame = render_frame(renderer, game, &tiles);
+        if post_process.is_enabled() {
+            post_process.apply(&frame)
+        } else {
+            frame
+        }
+    };
+
+    if let Some(frame) = frame {
+        for &buttons in replay.inputs.iter().take(frame) {
+            game.input(&Input::from_buttons(buttons));
+            game.set_camera_x(renderer.camera_state().smooth_view_x());
+            game.update(&levels);
+            // Nothing's played, but they'd pile up otherwise.
+            game.drain_sounds();
+            renderer.update(&game);
+        }
+
+        scale_frame(&draw_frame(&renderer, &game), scale).save(output)?;
+        println!("Saved update {} to {}", frame.min(replay.inputs.len()), output);
+        return Ok(());
+    }
+
+    let mut recorder = Recorder::create(output, scale)?;
+    recorder.add_frame(&draw_frame(&renderer, &game))?;
+
+    for &buttons in replay.inputs.iter() {
+        game.input(&Input::from_buttons(buttons));
+        game.set_camera_x(renderer.camera_state().smooth_view_x());
+        game.update(&levels);
+        game.drain_sounds();
+        renderer.update(&game);
+        recorder.add_frame(&draw_frame(&renderer, &game))?;
+
+        if game.quit() {
+            break;
+        }
+    }
+
+    println!("Saved {} frames to {}", recorder.frames(), output);
+    Ok(())
+}
\ No newline at end of file
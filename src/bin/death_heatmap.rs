@@ -0,0 +1,151 @@
+use std::{
+    env,
+    fmt::Write as FmtWrite,
+};
+
+use image::{RgbImage, Rgb, GenericImage};
+
+use ddave::{
+    Result, TILE_SIZE,
+    level::*,
+    deaths::*,
+};
+
+// Draws where players die over a map of each level, built from the extracted tiles
+// in the same way as level_ext builds `world.png`. Each grid cell is tinted red by
+// how many deaths happened in it, and every death is marked with a dot coloured by
+// its cause: yellow for hazards, magenta for monsters, and cyan for bullets.
+//
+// Usage: death_heatmap [--log <death log>] [level numbers...]
+//
+// Maps are written to `levels/deaths<n>.png`. With no levels given, every level with
+// a death in the log is drawn.
+
+const LEVEL_WIDTH: u32 = 100;
+const LEVEL_HEIGHT: u32 = 10;
+
+fn load_tiles() -> Result<Vec<RgbImage>> {
+    print!("Loading tiles...");
+    let mut tiles = Vec::with_capacity(158);
+
+    let mut name_buf = String::new();
+    for i in 0..158 {
+        write!(&mut name_buf, "tiles/tile{}.bmp", i)?;
+
+        let image = image::open(&name_buf)?;
+        tiles.push(image.to_rgb());
+
+        name_buf.clear();
+    }
+
+    println!("done");
+    Ok(tiles)
+}
+
+fn create_level_map(level: &Level, tiles: &[RgbImage]) -> RgbImage {
+    let mut map = RgbImage::new(LEVEL_WIDTH * TILE_SIZE, LEVEL_HEIGHT * TILE_SIZE);
+
+    for (i, tile) in level.tiles().iter().enumerate() {
+        let x = (i as u32 % LEVEL_WIDTH) * TILE_SIZE;
+        let y = (i as u32 / LEVEL_WIDTH) * TILE_SIZE;
+        map.copy_from(&tiles[tile.val() as usize], x, y);
+    }
+
+    map
+}
+
+fn blend(pixel: &mut Rgb<u8>, color: [u8; 3], alpha: f32) {
+    for (p, &c) in pixel.data.iter_mut().zip(color.iter()) {
+        *p = (*p as f32 * (1.0 - alpha) + c as f32 * alpha) as u8;
+    }
+}
+
+fn draw_heatmap(map: &mut RgbImage, deaths: &[&DeathEvent]) {
+    // Dave's position is his top left corner, so deaths are counted in the cell
+    // under the middle of him.
+    // Widened first, as positions come from a file that could have anything in it.
+    let centre = |d: &DeathEvent| (
+        (d.position.x as i32 + TILE_SIZE as i32 / 2).max(0) as u32,
+        (d.position.y as i32 + TILE_SIZE as i32 / 2).max(0) as u32,
+    );
+
+    let mut counts = vec![0u32; (LEVEL_WIDTH * LEVEL_HEIGHT) as usize];
+    for &d in deaths {
+        let (x, y) = centre(d);
+        let (grid_x, grid_y) = (x / TILE_SIZE, y / TILE_SIZE);
+        if grid_x < LEVEL_WIDTH && grid_y < LEVEL_HEIGHT {
+            counts[(grid_y * LEVEL_WIDTH + grid_x) as usize] += 1;
+        }
+    }
+
+    let max = counts.iter().cloned().max().unwrap_or(0).max(1);
+    for (x, y, pixel) in map.enumerate_pixels_mut() {
+        let count = counts[((y / TILE_SIZE) * LEVEL_WIDTH + x / TILE_SIZE) as usize];
+        if count > 0 {
+            blend(pixel, [255, 0, 0], 0.25 + 0.5 * count as f32 / max as f32);
+        }
+    }
+
+    for &d in deaths {
+        let color = match d.cause {
+            DeathCause::Hazard => [255, 255, 0],
+            DeathCause::Monster => [255, 0, 255],
+            DeathCause::MonsterBullet => [0, 255, 255],
+        };
+
+        let (x, y) = centre(d);
+        for (dx, dy) in (0..9).map(|i| (i % 3, i / 3)) {
+            let (px, py) = ((x + dx).saturating_sub(1), (y + dy).saturating_sub(1));
+            if px < map.width() && py < map.height() {
+                map.put_pixel(px, py, Rgb { data: color });
+            }
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let mut log_path = DEATH_LOG_FILE.to_owned();
+    let mut level_ids = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--log" => log_path = args.next().ok_or("--log needs a path")?,
+            num => {
+                let num: usize = num.parse()?;
+                level_ids.push(LevelId::new(num.wrapping_sub(1))?);
+            }
+        }
+    }
+
+    let deaths = load_deaths(&log_path)?;
+    println!("Read {} deaths from {}", deaths.len(), log_path);
+
+    if level_ids.is_empty() {
+        let mut level = Some(LevelId::first_level());
+        while let Some(l) = level {
+            if deaths.iter().any(|d| d.level == l) {
+                level_ids.push(l);
+            }
+            level = l.next();
+        }
+    }
+
+    let levels = load_levels()?;
+    let tiles = load_tiles()?;
+
+    for level in level_ids {
+        let level_deaths: Vec<&DeathEvent> = deaths.iter()
+            .filter(|d| d.level == level)
+            .collect();
+
+        let mut map = create_level_map(&levels[level], &tiles);
+        draw_heatmap(&mut map, &level_deaths);
+
+        let path = format!("levels/deaths{}.png", level.val() + 1);
+        map.save(&path)?;
+        println!("Level {}: {} deaths, saved to {}", level.val() + 1, level_deaths.len(), path);
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::{
+    env,
+    fs,
+    time::Instant,
+};
+
+use ddave::{
+    Result,
+    level::*,
+    solver::Solver,
+};
+
+// Checks that levels can be completed, by searching for a run through each of them.
+// Solutions are written to `replays/level<n>.txt`.
+//
+// Usage: level_solve [--max-nodes <count>] [level numbers...]
+//
+// Level numbers start at 1, as in the game. With no levels given, every level is
+// checked.
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let mut max_nodes = None;
+    let mut level_ids = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-nodes" => {
+                let count = args.next().ok_or("--max-nodes needs a count")?;
+                max_nodes = Some(count.parse()?);
+            },
+            num => {
+                let num: usize = num.parse()?;
+                level_ids.push(LevelId::new(num.wrapping_sub(1))?);
+            }
+        }
+    }
+
+    if level_ids.is_empty() {
+        let mut level = Some(LevelId::first_level());
+        while let Some(l) = level {
+            level_ids.push(l);
+            level = l.next();
+        }
+    }
+
+    let levels = load_levels()?;
+    let mut solver = Solver::new(&levels);
+    if let Some(max_nodes) = max_nodes {
+        solver = solver.max_nodes(max_nodes);
+    }
+
+    fs::create_dir_all("replays")?;
+
+    let mut failed = 0;
+    for level in level_ids {
+        print!("Solving level {}...", level.val() + 1);
+
+        let start = Instant::now();
+        let (replay, stats) = solver.solve(level);
+
+        match replay {
+            Some(replay) => {
+                let path = format!("replays/level{}.txt", level.val() + 1);
+                replay.save_file(&path)?;
+                println!("done in {} updates ({} nodes, {:.1}s), saved to {}",
+                    replay.inputs.len(), stats.nodes_expanded, start.elapsed().as_secs_f64(), path);
+            },
+            None => {
+                failed += 1;
+                println!("no solution found ({} nodes, {:.1}s)",
+                    stats.nodes_expanded, start.elapsed().as_secs_f64());
+            }
+        }
+    }
+
+    if failed > 0 {
+        Err(format!("{} level(s) could not be solved", failed))?;
+    }
+
+    Ok(())
+}
\ No newline at end of file
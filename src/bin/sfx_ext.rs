@@ -0,0 +1,267 @@
+use std::{
+    fs::File,
+    io::{Read, BufWriter, Write},
+    error::Error,
+};
+use byteorder::{ByteOrder, ReadBytesExt, LittleEndian};
+
+use ddave::audio::{SoundEffect, Tone, SAMPLE_RATE, synthesize, write_wav};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+// The PC-speaker sounds are expected to be stored the same way as in the other
+// Softdisk games of the time, as a block starting with "SND\0":
+//
+//  0x00    "SND\0"
+//  0x04    u16     Length of the whole block.
+//  0x06    u16     Unused.
+//  0x08    u16     Number of sounds.
+//  0x0A    [u8; 6] Padding.
+//  0x10    Sound table, 16 bytes per sound:
+//              u16      Offset of the sound data from the start of the block.
+//              u8       Priority.
+//              u8       Rate.
+//              [u8; 12] Name, null padded.
+//
+// Each sound is a sequence of u16 timer divisors, one per 140Hz tick, ending in
+// 0xFFFF. The speaker frequency is the PIT clock divided by the divisor, and a
+// divisor of 0 is silence.
+//
+// That layout comes from the Keen games, not from dave.exe itself, so nothing is
+// taken on trust. The block has to be the only one in the file that checks out in
+// full, with every sound inside it and ending where it should, and anything else
+// stops the extractor with an error. The game then keeps its built-in sounds.
+const SND_SIGNATURE: &[u8] = b"SND\0";
+const SND_HEADER_LEN: usize = 16;
+const SND_ENTRY_LEN: usize = 16;
+const SND_END: u16 = 0xFFFF;
+
+const PIT_CLOCK: u32 = 1_193_181;
+const TICK_RATE: u32 = 140;
+
+// Anything under this would be over 62kHz, which is well out of hearing, and out
+// of a u16, so it's written as silence.
+const MIN_DIVISOR: u16 = 19;
+
+// Which of the original's sounds each effect is guessed to be, by its place in the
+// table. The order hasn't been checked against a copy of the game, so the names
+// in the table are printed next to the effects they've gone to, and the game only
+// uses the sounds with `--sounds`. The game picks sounds by the effect's name, so
+// renaming them in the file fixes any that are wrong, and the rest are only written
+// out to listen to.
+const EFFECT_SOUNDS: [(usize, SoundEffect); 9] = [
+    (0, SoundEffect::Jump),
+    (1, SoundEffect::Gem),
+    (2, SoundEffect::Trophy),
+    (3, SoundEffect::Gunfire),
+    (4, SoundEffect::MonsterDeath),
+    (5, SoundEffect::DaveDeath),
+    (6, SoundEffect::Jetpack),
+    (7, SoundEffect::Door),
+    (8, SoundEffect::ExtraLife),
+];
+
+struct Sound {
+    // The name in the table, which is only shown, not used.
+    original_name: String,
+    name: String,
+    tones: Vec<Tone>,
+}
+
+fn sound_count(block: &[u8]) -> usize {
+    (&block[8..]).read_u16::<LittleEndian>().unwrap_or(0) as usize
+}
+
+// Checks a possible sound block over, returning it cut down to its own length.
+fn check_sound_block(block: &[u8]) -> std::result::Result<&[u8], String> {
+    let mut header = block.get(4..SND_HEADER_LEN).ok_or("the header is cut off")?;
+    let length = header.read_u16::<LittleEndian>().unwrap_or(0) as usize;
+    let count = sound_count(block);
+    let table_end = SND_HEADER_LEN + count * SND_ENTRY_LEN;
+    let needed = EFFECT_SOUNDS.iter().map(|&(i, _)| i + 1).max().unwrap_or(0);
+
+    if length > block.len() {
+        return Err(format!("its length ({:#x}) runs past the end of the file", length));
+    }
+    if count < needed {
+        return Err(format!("it has {} sounds, and at least {} are needed", count, needed));
+    }
+    if table_end > length {
+        return Err(format!("its table of {} sounds doesn't fit in its length ({:#x})", count, length));
+    }
+
+    let block = &block[..length];
+    for i in 0..count {
+        let mut entry = &block[SND_HEADER_LEN + i * SND_ENTRY_LEN..];
+        let offset = entry.read_u16::<LittleEndian>().unwrap_or(0) as usize;
+        if offset < table_end || offset >= length {
+            return Err(format!("sound {} starts outside the block, at {:#x}", i, offset));
+        }
+
+        let ends = block[offset..].chunks_exact(2)
+            .any(|d| LittleEndian::read_u16(d) == SND_END);
+        if !ends {
+            return Err(format!("sound {} doesn't end inside the block", i));
+        }
+    }
+
+    Ok(block)
+}
+
+fn find_sound_block(exe: &[u8]) -> Result<&[u8]> {
+    print!("Finding sound table...");
+
+    let candidates: Vec<(usize, std::result::Result<&[u8], String>)> = exe.windows(SND_SIGNATURE.len())
+        .enumerate()
+        .filter(|(_, w)| *w == SND_SIGNATURE)
+        .map(|(addr, _)| (addr, check_sound_block(&exe[addr..])))
+        .collect();
+
+    let valid: Vec<(usize, &[u8])> = candidates.iter()
+        .filter_map(|(addr, block)| block.as_ref().ok().map(|&b| (*addr, b)))
+        .collect();
+
+    match valid.as_slice() {
+        [(addr, block)] => {
+            println!("found at {:#x}", addr);
+            Ok(block)
+        },
+        [] => {
+            println!("failed");
+            let reasons: Vec<String> = candidates.iter()
+                .filter_map(|(addr, block)| block.as_ref().err().map(|e| format!("at {:#x}, {}", addr, e)))
+                .collect();
+
+            if reasons.is_empty() {
+                Err("orig/dave.exe has no \"SND\\0\" sound table, so its sounds can't be extracted")?
+            } else {
+                Err(format!("orig/dave.exe has no sound table in the expected layout ({})", reasons.join("; ")))?
+            }
+        },
+        _ => {
+            println!("failed");
+            let addrs: Vec<String> = valid.iter().map(|(addr, _)| format!("{:#x}", addr)).collect();
+            Err(format!("orig/dave.exe has more than one possible sound table ({}), so it's not clear which to use", addrs.join(", ")))?
+        },
+    }
+}
+
+fn divisor_frequency(divisor: u16) -> u16 {
+    if divisor < MIN_DIVISOR {
+        0
+    } else {
+        (PIT_CLOCK / divisor as u32) as u16
+    }
+}
+
+fn read_sounds(block: &[u8]) -> Result<Vec<Sound>> {
+    print!("Reading sounds...");
+
+    let count = sound_count(block);
+    let mut sounds = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut entry = &block[SND_HEADER_LEN + i * SND_ENTRY_LEN..];
+        let offset = entry.read_u16::<LittleEndian>()? as usize;
+        let _priority = entry.read_u8()?;
+        let _rate = entry.read_u8()?;
+
+        let original_name: String = entry[..12].iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as char)
+            .collect();
+
+        let name = match EFFECT_SOUNDS.iter().find(|&&(index, _)| index == i) {
+            Some((_, effect)) => effect.name().to_owned(),
+            None => format!("unused{}", i),
+        };
+
+        // Collapse runs of the same frequency into a single tone.
+        let mut tones: Vec<(u16, u32)> = Vec::new();
+        let mut data = &block[offset..];
+        loop {
+            let divisor = data.read_u16::<LittleEndian>()?;
+            if divisor == SND_END {
+                break;
+            }
+
+            let frequency = divisor_frequency(divisor);
+            match tones.last_mut() {
+                Some((f, ticks)) if *f == frequency => *ticks += 1,
+                _ => tones.push((frequency, 1)),
+            }
+        }
+
+        let tones = tones.into_iter()
+            .map(|(frequency, ticks)| Tone {
+                frequency,
+                duration_ms: (ticks * 1000 / TICK_RATE) as u16,
+            })
+            .collect();
+
+        sounds.push(Sound { original_name, name, tones });
+    }
+
+    println!("done");
+    for (i, sound) in sounds.iter().enumerate() {
+        println!("  {:2} {:12} -> {}", i, sound.original_name, sound.name);
+    }
+    Ok(sounds)
+}
+
+fn write_data_file(sounds: &[Sound]) -> Result<()> {
+    print!("Saving sound data...");
+
+    let mut file = BufWriter::new(File::create("sfx/sounds.txt")?);
+    writeln!(file, "# Dangerous Dave PC-speaker sounds, read from orig/dave.exe.")?;
+    writeln!(file, "#")?;
+    writeln!(file, "# Which sound goes with which effect is a guess, by its place in the table, and")?;
+    writeln!(file, "# hasn't been checked against the original, so the game only uses this file when")?;
+    writeln!(file, "# it's given with `--sounds sfx/sounds.txt`.")?;
+    writeln!(file, "#")?;
+    writeln!(file, "# Each sound starts with a `sound <name>` line, followed by one line per tone")?;
+    writeln!(file, "# giving its frequency in Hz and its duration in milliseconds. A frequency of")?;
+    writeln!(file, "# 0 is silence.")?;
+    writeln!(file, "#")?;
+    writeln!(file, "# The game picks sounds by name: jump, gem, trophy, gunfire, monster_death,")?;
+    writeln!(file, "# dave_death, jetpack, door and extra_life. Renaming a sound here changes which")?;
+    writeln!(file, "# effect it's used for. Effects without a sound here use the built-in versions.")?;
+
+    for sound in sounds {
+        writeln!(file)?;
+        writeln!(file, "# {}", sound.original_name)?;
+        writeln!(file, "sound {}", sound.name)?;
+        for tone in &sound.tones {
+            writeln!(file, "{} {}", tone.frequency, tone.duration_ms)?;
+        }
+    }
+
+    file.flush()?;
+    println!("done");
+    Ok(())
+}
+
+fn write_wavs(sounds: &[Sound]) -> Result<()> {
+    print!("Saving preview WAVs...");
+
+    for (i, sound) in sounds.iter().enumerate() {
+        let file = BufWriter::new(File::create(format!("sfx/sound{}_{}.wav", i, sound.name))?);
+        write_wav(file, &synthesize(&sound.tones, SAMPLE_RATE), SAMPLE_RATE)?;
+    }
+
+    println!("done");
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let mut exe = Vec::new();
+    File::open("orig/dave.exe")?.read_to_end(&mut exe)?;
+
+    let block = find_sound_block(&exe)?;
+    let sounds = read_sounds(block)?;
+    write_data_file(&sounds)?;
+    write_wavs(&sounds)?;
+
+    println!("Which sound goes with which effect is a guess, so listen to the WAVs before playing with --sounds sfx/sounds.txt");
+
+    Ok(())
+}
\ No newline at end of file
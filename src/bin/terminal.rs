@@ -0,0 +1,117 @@
+use std::{
+    env,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    execute,
+    cursor::{Hide, Show},
+    event::{self, Event, KeyboardEnhancementFlags, PushKeyboardEnhancementFlags, PopKeyboardEnhancementFlags},
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, Clear, ClearType},
+};
+
+use ddave::{
+    Result, UPDATES_PER_SECOND,
+    game::Game,
+    level::*,
+    input::Input,
+    tileset::load_tile_images,
+    terminal::*,
+};
+
+// Plays the game in the terminal, with no window.
+//
+// Usage: terminal [--symbols] [--level <n>]
+//
+// The screen is drawn in coloured half blocks by default, which needs a terminal
+// with 24-bit colour. `--symbols` draws a tile as two characters instead, which
+// works anywhere, and doesn't need the tiles. See terminal.rs for the keys.
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let mut mode = TerminalMode::Pixels;
+    let mut level = LevelId::first_level();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbols" => mode = TerminalMode::Symbols,
+            "--level" => {
+                let num: usize = args.next().ok_or("--level needs a level number")?.parse()?;
+                level = LevelId::new(num.wrapping_sub(1))?;
+            },
+            _ => Err("Usage: terminal [--symbols] [--level <n>]")?,
+        }
+    }
+
+    let levels = load_levels()?;
+    let tiles = match mode {
+        TerminalMode::Pixels => Some(load_tile_images()?),
+        TerminalMode::Symbols => None,
+    };
+    let mut renderer = TerminalRenderer::new(mode, tiles)?;
+    let mut game = Game::init_level(level, &levels);
+
+    let session = Session::start()?;
+    let mut input = TerminalInput::new(session.reports_releases);
+    let mut out = io::BufWriter::new(io::stdout());
+
+    let update_time = Duration::from_secs(1) / UPDATES_PER_SECOND as u32;
+    let mut next_update = Instant::now();
+
+    while !input.quit() && !game.quit() {
+        // Keys are read as they come in until it's time for the next update.
+        while event::poll(next_update.saturating_duration_since(Instant::now()))? {
+            match event::read()? {
+                Event::Key(key) => input.handle_key(key),
+                Event::Resize(..) => execute!(out, Clear(ClearType::All))?,
+                _ => (),
+            }
+        }
+
+        game.input(&Input::from_buttons(input.next_buttons()));
+        game.update(&levels);
+        // There's no sound in the terminal, but they'd pile up otherwise.
+        game.drain_sounds();
+        renderer.update(&game);
+        renderer.draw(&mut out, &game, terminal::size()?, "Arrows move, Space fires, J jetpack, Q quits")?;
+
+        // If drawing falls behind, it catches up on the time rather than the updates.
+        next_update = (next_update + update_time).max(Instant::now());
+    }
+
+    drop(session);
+    println!("Score: {}", game.score());
+    Ok(())
+}
+
+// Puts the terminal back how it was, even if the game stops with an error.
+struct Session {
+    reports_releases: bool,
+}
+
+impl Session {
+    fn start() -> Result<Session> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            execute!(out, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+
+        Ok(Session { reports_releases })
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.reports_releases {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, Show, LeaveAlternateScreen);
+        let _ = out.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
\ No newline at end of file
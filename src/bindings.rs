@@ -0,0 +1,383 @@
+use std::{
+    env,
+    fmt,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use piston_window::keyboard::Key;
+
+use crate::Result;
+
+// Which keys do what. The default is the original's, and each user's own bindings
+// are kept in `ddave/keys.txt` in their config folder (`$XDG_CONFIG_HOME`, or
+// `~/.config`, or `%APPDATA%` on Windows), one action a line, with any number of
+// keys each:
+//
+//  # action key...
+//  right Right D
+//  jetpack LAlt J
+//
+// Keys go by their Piston names (see KEY_NAMES), in any case. A key can only do
+// one thing, so it's taken from the first action that has it. Actions that aren't
+// in the file keep their original keys, and an action with no keys after it has
+// none. The keys the game keeps for itself, like Esc for the screen that changes
+// them, can't be bound (see RESERVED_KEYS).
+//
+// The tool-assisted mode's controls are here too (see tas.rs). Each of the
+// save_slot and load_slot keys goes with a slot, in order, so the first is slot 1.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Right,
+    Left,
+    Jump,
+    Down,
+    Fire,
+    Jetpack,
+    Rewind,
+    Pause,
+    Step,
+    TasMode,
+    Export,
+    Import,
+    SaveSlot,
+    LoadSlot,
+}
+
+impl Action {
+    pub const ALL: [Action; 14] = [
+        Action::Right,
+        Action::Left,
+        Action::Jump,
+        Action::Down,
+        Action::Fire,
+        Action::Jetpack,
+        Action::Rewind,
+        Action::Pause,
+        Action::Step,
+        Action::TasMode,
+        Action::Export,
+        Action::Import,
+        Action::SaveSlot,
+        Action::LoadSlot,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Right => "right",
+            Action::Left => "left",
+            Action::Jump => "jump",
+            Action::Down => "down",
+            Action::Fire => "fire",
+            Action::Jetpack => "jetpack",
+            Action::Rewind => "rewind",
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::TasMode => "tas_mode",
+            Action::Export => "export",
+            Action::Import => "import",
+            Action::SaveSlot => "save_slot",
+            Action::LoadSlot => "load_slot",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Action> {
+        Action::ALL.iter()
+            .copied()
+            .find(|a| a.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown action: {}", s).into())
+    }
+}
+
+// Every key Piston knows, by the name it goes by in the file, which is the same as
+// its name in piston's keyboard.rs.
+const KEY_NAMES: [(Key, &str); 235] = [
+    (Key::Backspace, "Backspace"), (Key::Tab, "Tab"), (Key::Return, "Return"),
+    (Key::Escape, "Escape"), (Key::Space, "Space"), (Key::Exclaim, "Exclaim"),
+    (Key::Quotedbl, "Quotedbl"), (Key::Hash, "Hash"), (Key::Dollar, "Dollar"),
+    (Key::Percent, "Percent"), (Key::Ampersand, "Ampersand"), (Key::Quote, "Quote"),
+    (Key::LeftParen, "LeftParen"), (Key::RightParen, "RightParen"),
+    (Key::Asterisk, "Asterisk"), (Key::Plus, "Plus"), (Key::Comma, "Comma"),
+    (Key::Minus, "Minus"), (Key::Period, "Period"), (Key::Slash, "Slash"),
+    (Key::D0, "D0"), (Key::D1, "D1"), (Key::D2, "D2"), (Key::D3, "D3"), (Key::D4, "D4"),
+    (Key::D5, "D5"), (Key::D6, "D6"), (Key::D7, "D7"), (Key::D8, "D8"), (Key::D9, "D9"),
+    (Key::Colon, "Colon"), (Key::Semicolon, "Semicolon"), (Key::Less, "Less"),
+    (Key::Equals, "Equals"), (Key::Greater, "Greater"), (Key::Question, "Question"),
+    (Key::At, "At"), (Key::LeftBracket, "LeftBracket"), (Key::Backslash, "Backslash"),
+    (Key::RightBracket, "RightBracket"), (Key::Caret, "Caret"),
+    (Key::Underscore, "Underscore"), (Key::Backquote, "Backquote"), (Key::A, "A"),
+    (Key::B, "B"), (Key::C, "C"), (Key::D, "D"), (Key::E, "E"), (Key::F, "F"),
+    (Key::G, "G"), (Key::H, "H"), (Key::I, "I"), (Key::J, "J"), (Key::K, "K"),
+    (Key::L, "L"), (Key::M, "M"), (Key::N, "N"), (Key::O, "O"), (Key::P, "P"),
+    (Key::Q, "Q"), (Key::R, "R"), (Key::S, "S"), (Key::T, "T"), (Key::U, "U"),
+    (Key::V, "V"), (Key::W, "W"), (Key::X, "X"), (Key::Y, "Y"), (Key::Z, "Z"),
+    (Key::Delete, "Delete"), (Key::CapsLock, "CapsLock"), (Key::F1, "F1"),
+    (Key::F2, "F2"), (Key::F3, "F3"), (Key::F4, "F4"), (Key::F5, "F5"), (Key::F6, "F6"),
+    (Key::F7, "F7"), (Key::F8, "F8"), (Key::F9, "F9"), (Key::F10, "F10"),
+    (Key::F11, "F11"), (Key::F12, "F12"), (Key::PrintScreen, "PrintScreen"),
+    (Key::ScrollLock, "ScrollLock"), (Key::Pause, "Pause"), (Key::Insert, "Insert"),
+    (Key::Home, "Home"), (Key::PageUp, "PageUp"), (Key::End, "End"),
+    (Key::PageDown, "PageDown"), (Key::Right, "Right"), (Key::Left, "Left"),
+    (Key::Down, "Down"), (Key::Up, "Up"), (Key::NumLockClear, "NumLockClear"),
+    (Key::NumPadDivide, "NumPadDivide"), (Key::NumPadMultiply, "NumPadMultiply"),
+    (Key::NumPadMinus, "NumPadMinus"), (Key::NumPadPlus, "NumPadPlus"),
+    (Key::NumPadEnter, "NumPadEnter"), (Key::NumPad1, "NumPad1"),
+    (Key::NumPad2, "NumPad2"), (Key::NumPad3, "NumPad3"), (Key::NumPad4, "NumPad4"),
+    (Key::NumPad5, "NumPad5"), (Key::NumPad6, "NumPad6"), (Key::NumPad7, "NumPad7"),
+    (Key::NumPad8, "NumPad8"), (Key::NumPad9, "NumPad9"), (Key::NumPad0, "NumPad0"),
+    (Key::NumPadPeriod, "NumPadPeriod"), (Key::Application, "Application"),
+    (Key::Power, "Power"), (Key::NumPadEquals, "NumPadEquals"), (Key::F13, "F13"),
+    (Key::F14, "F14"), (Key::F15, "F15"), (Key::F16, "F16"), (Key::F17, "F17"),
+    (Key::F18, "F18"), (Key::F19, "F19"), (Key::F20, "F20"), (Key::F21, "F21"),
+    (Key::F22, "F22"), (Key::F23, "F23"), (Key::F24, "F24"), (Key::Execute, "Execute"),
+    (Key::Help, "Help"), (Key::Menu, "Menu"), (Key::Select, "Select"),
+    (Key::Stop, "Stop"), (Key::Again, "Again"), (Key::Undo, "Undo"), (Key::Cut, "Cut"),
+    (Key::Copy, "Copy"), (Key::Paste, "Paste"), (Key::Find, "Find"),
+    (Key::Mute, "Mute"), (Key::VolumeUp, "VolumeUp"), (Key::VolumeDown, "VolumeDown"),
+    (Key::NumPadComma, "NumPadComma"), (Key::NumPadEqualsAS400, "NumPadEqualsAS400"),
+    (Key::AltErase, "AltErase"), (Key::Sysreq, "Sysreq"), (Key::Cancel, "Cancel"),
+    (Key::Clear, "Clear"), (Key::Prior, "Prior"), (Key::Return2, "Return2"),
+    (Key::Separator, "Separator"), (Key::Out, "Out"), (Key::Oper, "Oper"),
+    (Key::ClearAgain, "ClearAgain"), (Key::CrSel, "CrSel"), (Key::ExSel, "ExSel"),
+    (Key::NumPad00, "NumPad00"), (Key::NumPad000, "NumPad000"),
+    (Key::ThousandsSeparator, "ThousandsSeparator"),
+    (Key::DecimalSeparator, "DecimalSeparator"), (Key::CurrencyUnit, "CurrencyUnit"),
+    (Key::CurrencySubUnit, "CurrencySubUnit"),
+    (Key::NumPadLeftParen, "NumPadLeftParen"),
+    (Key::NumPadRightParen, "NumPadRightParen"),
+    (Key::NumPadLeftBrace, "NumPadLeftBrace"),
+    (Key::NumPadRightBrace, "NumPadRightBrace"), (Key::NumPadTab, "NumPadTab"),
+    (Key::NumPadBackspace, "NumPadBackspace"), (Key::NumPadA, "NumPadA"),
+    (Key::NumPadB, "NumPadB"), (Key::NumPadC, "NumPadC"), (Key::NumPadD, "NumPadD"),
+    (Key::NumPadE, "NumPadE"), (Key::NumPadF, "NumPadF"), (Key::NumPadXor, "NumPadXor"),
+    (Key::NumPadPower, "NumPadPower"), (Key::NumPadPercent, "NumPadPercent"),
+    (Key::NumPadLess, "NumPadLess"), (Key::NumPadGreater, "NumPadGreater"),
+    (Key::NumPadAmpersand, "NumPadAmpersand"),
+    (Key::NumPadDblAmpersand, "NumPadDblAmpersand"),
+    (Key::NumPadVerticalBar, "NumPadVerticalBar"),
+    (Key::NumPadDblVerticalBar, "NumPadDblVerticalBar"),
+    (Key::NumPadColon, "NumPadColon"), (Key::NumPadHash, "NumPadHash"),
+    (Key::NumPadSpace, "NumPadSpace"), (Key::NumPadAt, "NumPadAt"),
+    (Key::NumPadExclam, "NumPadExclam"), (Key::NumPadMemStore, "NumPadMemStore"),
+    (Key::NumPadMemRecall, "NumPadMemRecall"), (Key::NumPadMemClear, "NumPadMemClear"),
+    (Key::NumPadMemAdd, "NumPadMemAdd"), (Key::NumPadMemSubtract, "NumPadMemSubtract"),
+    (Key::NumPadMemMultiply, "NumPadMemMultiply"),
+    (Key::NumPadMemDivide, "NumPadMemDivide"),
+    (Key::NumPadPlusMinus, "NumPadPlusMinus"), (Key::NumPadClear, "NumPadClear"),
+    (Key::NumPadClearEntry, "NumPadClearEntry"), (Key::NumPadBinary, "NumPadBinary"),
+    (Key::NumPadOctal, "NumPadOctal"), (Key::NumPadDecimal, "NumPadDecimal"),
+    (Key::NumPadHexadecimal, "NumPadHexadecimal"), (Key::LCtrl, "LCtrl"),
+    (Key::LShift, "LShift"), (Key::LAlt, "LAlt"), (Key::LGui, "LGui"),
+    (Key::RCtrl, "RCtrl"), (Key::RShift, "RShift"), (Key::RAlt, "RAlt"),
+    (Key::RGui, "RGui"), (Key::Mode, "Mode"), (Key::AudioNext, "AudioNext"),
+    (Key::AudioPrev, "AudioPrev"), (Key::AudioStop, "AudioStop"),
+    (Key::AudioPlay, "AudioPlay"), (Key::AudioMute, "AudioMute"),
+    (Key::MediaSelect, "MediaSelect"), (Key::Www, "Www"), (Key::Mail, "Mail"),
+    (Key::Calculator, "Calculator"), (Key::Computer, "Computer"),
+    (Key::AcSearch, "AcSearch"), (Key::AcHome, "AcHome"), (Key::AcBack, "AcBack"),
+    (Key::AcForward, "AcForward"), (Key::AcStop, "AcStop"),
+    (Key::AcRefresh, "AcRefresh"), (Key::AcBookmarks, "AcBookmarks"),
+    (Key::BrightnessDown, "BrightnessDown"), (Key::BrightnessUp, "BrightnessUp"),
+    (Key::DisplaySwitch, "DisplaySwitch"), (Key::KbdIllumToggle, "KbdIllumToggle"),
+    (Key::KbdIllumDown, "KbdIllumDown"), (Key::KbdIllumUp, "KbdIllumUp"),
+    (Key::Eject, "Eject"), (Key::Sleep, "Sleep"),
+];
+
+// The keys the game keeps for itself, whatever the bindings say, and what for.
+const RESERVED_KEYS: [(Key, &str); 6] = [
+    (Key::Tab, "the debug overlay"),
+    (Key::Return, "skipping the death replay"),
+    (Key::Escape, "the key bindings screen"),
+    (Key::F10, "recording"),
+    (Key::F11, "fullscreen"),
+    (Key::F12, "screenshots"),
+];
+
+pub fn key_name(key: Key) -> &'static str {
+    KEY_NAMES.iter()
+        .find(|&&(k, _)| k == key)
+        .map_or("Unknown", |&(_, name)| name)
+}
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    KEY_NAMES.iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name))
+        .map(|&(key, _)| key)
+}
+
+// Fails if the game keeps the key for itself, saying what for.
+fn check_bindable(key: Key) -> Result<()> {
+    match RESERVED_KEYS.iter().find(|&&(k, _)| k == key) {
+        Some((_, used_for)) => Err(format!("{} can't be bound, as it's kept for {}", key_name(key), used_for))?,
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    // In the same order as Action::ALL.
+    keys: Vec<Vec<Key>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            keys: vec![
+                vec![Key::Right],
+                vec![Key::Left],
+                vec![Key::Up],
+                vec![Key::Down],
+                vec![Key::LCtrl],
+                vec![Key::LAlt],
+                vec![Key::Backspace],
+                vec![Key::P],
+                vec![Key::Period],
+                vec![Key::M],
+                vec![Key::E],
+                vec![Key::I],
+                vec![Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9],
+                vec![Key::D1, Key::D2, Key::D3, Key::D4, Key::D5, Key::D6, Key::D7, Key::D8, Key::D9],
+            ],
+        }
+    }
+}
+
+impl KeyBindings {
+    // No keys for anything.
+    fn empty() -> KeyBindings {
+        KeyBindings {
+            keys: vec![Vec::new(); Action::ALL.len()],
+        }
+    }
+
+    fn index(action: Action) -> usize {
+        Action::ALL.iter().position(|&a| a == action).unwrap_or(0)
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        &self.keys[KeyBindings::index(action)]
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        Action::ALL.iter()
+            .copied()
+            .find(|&a| self.keys(a).contains(&key))
+    }
+
+    // Adds the key to the action, taking it from whatever it did before, unless the
+    // game keeps it for itself.
+    pub fn bind(&mut self, action: Action, key: Key) -> Result<()> {
+        check_bindable(key)?;
+
+        for keys in self.keys.iter_mut() {
+            keys.retain(|&k| k != key);
+        }
+        self.keys[KeyBindings::index(action)].push(key);
+        Ok(())
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.keys[KeyBindings::index(action)].clear();
+    }
+
+    pub fn load<R: BufRead>(input: R) -> Result<KeyBindings> {
+        let mut bindings = KeyBindings::empty();
+        let mut listed = Vec::new();
+
+        for (line_num, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let action: Action = parts.next().unwrap_or("").parse()
+                .map_err(|e| format!("Invalid binding on line {}: {}: {}", line_num + 1, line, e))?;
+            listed.push(action);
+
+            for name in parts {
+                let key = parse_key(name)
+                    .ok_or_else(|| format!("Invalid binding on line {}: {}: Unknown key: {}", line_num + 1, line, name))?;
+                check_bindable(key)
+                    .map_err(|e| format!("Invalid binding on line {}: {}: {}", line_num + 1, line, e))?;
+                if bindings.action(key).is_none() {
+                    bindings.keys[KeyBindings::index(action)].push(key);
+                }
+            }
+        }
+
+        let defaults = KeyBindings::default();
+        for &action in Action::ALL.iter().filter(|a| !listed.contains(a)) {
+            for &key in defaults.keys(action) {
+                if bindings.action(key).is_none() {
+                    bindings.keys[KeyBindings::index(action)].push(key);
+                }
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn save<W: Write>(&self, mut out: W) -> Result<()> {
+        writeln!(out, "# action key...")?;
+        write!(out, "{}", self)?;
+
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<KeyBindings> {
+        KeyBindings::load(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    // The file the user's own bindings go in, if there's anywhere to put it.
+    pub fn user_file() -> Option<PathBuf> {
+        let config_dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        };
+
+        config_dir.map(|dir| dir.join("ddave").join("keys.txt"))
+    }
+
+    // The bindings in the file, or the defaults if it hasn't been written yet.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<KeyBindings> {
+        if path.as_ref().exists() {
+            KeyBindings::load_file(path)
+        } else {
+            Ok(KeyBindings::default())
+        }
+    }
+}
+
+impl fmt::Display for KeyBindings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &action in Action::ALL.iter() {
+            write!(f, "{}", action.name())?;
+            for &key in self.keys(action) {
+                write!(f, " {}", key_name(key))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
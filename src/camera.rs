@@ -0,0 +1,124 @@
+use crate::{
+    TILE_SIZE,
+    game::Game,
+    level::LevelId,
+    framebuffer::screen_width,
+};
+
+// Where the view of the level is, in pixels from its left edge.
+//
+// The classic camera is the game's own view, which jumps a whole tile at a time
+// when Dave gets near the edge of the screen. The smooth camera follows Dave a
+// pixel at a time instead, easing towards him once he leaves a dead zone around
+// the middle of the screen.
+//
+// The game keeps its own view either way, which is what the classic camera shows.
+// Monsters only shoot at Dave, and bullets only fly, while they're on the screen,
+// so with the smooth camera the game is told where it is before every update (see
+// Game::set_camera_x), and goes by that instead. That makes the camera part of how
+// the game plays, so a replay has to be played back with the same one.
+
+#[derive(Debug, Copy, Clone)]
+pub struct SmoothCamera {
+    // How far, in pixels, Dave can move either side of the middle of the screen
+    // before the camera follows.
+    pub dead_zone: f64,
+    // How much of the way to its target the camera moves each update, from just
+    // above 0 (slow) to 1 (instant).
+    pub easing: f64,
+}
+
+impl Default for SmoothCamera {
+    fn default() -> Self {
+        SmoothCamera {
+            dead_zone: 24.0,
+            easing: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum CameraMode {
+    Classic,
+    Smooth(SmoothCamera),
+}
+
+#[derive(Debug, Clone)]
+pub struct Camera {
+    mode: CameraMode,
+    x: f64,
+    level: Option<LevelId>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(CameraMode::Classic)
+    }
+}
+
+impl Camera {
+    pub fn new(mode: CameraMode) -> Camera {
+        Camera {
+            mode,
+            x: 0.0,
+            level: None,
+        }
+    }
+
+    pub fn classic_view_x(game: &Game) -> f64 {
+        (game.view_x() as u32 * TILE_SIZE) as f64
+    }
+
+    // Called after every update.
+    pub fn update(&mut self, game: &Game) {
+        let settings = match self.mode {
+            CameraMode::Classic => return,
+            CameraMode::Smooth(settings) => settings,
+        };
+
+        // The level is 100 tiles across, and the screen shows some of them.
+        let width = screen_width(game) as f64;
+        let max_view_x = (100 * TILE_SIZE) as f64 - width;
+
+        let centre = (game.dave().pixel_position.x + TILE_SIZE as i16 / 2) as f64;
+        let offset = centre - (self.x + width / 2.0);
+
+        let target = if offset > settings.dead_zone {
+            self.x + offset - settings.dead_zone
+        } else if offset < -settings.dead_zone {
+            self.x + offset + settings.dead_zone
+        } else {
+            self.x
+        };
+        let target = target.clamp(0.0, max_view_x);
+
+        // Jump straight there on a new level, or if Dave has been moved a long way
+        // at once, by restarting or loading a save.
+        let jump = self.level != Some(game.current_level())
+            || (target - self.x).abs() > width / 2.0;
+
+        if jump {
+            self.x = target;
+        } else {
+            self.x += (target - self.x) * settings.easing.clamp(0.0, 1.0);
+        }
+
+        self.level = Some(game.current_level());
+    }
+
+    // Where the smooth camera is, for the game, or None for the classic one.
+    pub fn smooth_view_x(&self) -> Option<i16> {
+        match self.mode {
+            CameraMode::Classic => None,
+            CameraMode::Smooth(_) => Some(self.x.round() as i16),
+        }
+    }
+
+    pub fn view_x(&self, game: &Game) -> f64 {
+        match self.mode {
+            CameraMode::Classic => Camera::classic_view_x(game),
+            // Kept to whole pixels, so the tiles don't shimmer as it eases.
+            CameraMode::Smooth(_) => self.x.round(),
+        }
+    }
+}
\ No newline at end of file
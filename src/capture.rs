@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use gif::{Encoder, Frame, Repeat, SetParameter};
+use image::{RgbaImage, FilterType};
+use piston_window::{
+    ButtonArgs, ButtonState, Button,
+    keyboard::Key,
+};
+
+use crate::{
+    Result, UPDATES_PER_SECOND,
+    game::Game,
+    renderer::Renderer,
+    framebuffer::{Framebuffer, screen_width},
+    tileset::TileImages,
+};
+
+// Screenshots and recordings. Frames are always drawn offscreen with the framebuffer,
+// at the game's own resolution, and then scaled up by a whole number if asked for, so they
+// come out the same whatever the window looks like.
+//
+//  F12  Save a screenshot to `screenshots`.
+//  F10  Start or stop recording an animated GIF to `recordings`.
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+pub const RECORDING_DIR: &str = "recordings";
+
+// How hard the GIF encoder works at picking a palette, from 1 (best) to 30 (fastest),
+// for frames with too many colours to fit. That never happens with the original
+// tiles.
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+pub fn render_frame(renderer: &Renderer, game: &Game, tiles: &TileImages) -> RgbaImage {
+    let mut framebuffer = Framebuffer::new(tiles, screen_width(game));
+    renderer.draw(&mut framebuffer, game);
+    framebuffer.into_image()
+}
+
+pub fn scale_frame(frame: &RgbaImage, scale: u32) -> RgbaImage {
+    if scale <= 1 {
+        return frame.clone();
+    }
+
+    image::imageops::resize(frame, frame.width() * scale, frame.height() * scale, FilterType::Nearest)
+}
+
+// The first path of the form `<dir>/<prefix><n>.<extension>` that isn't taken yet.
+fn next_free_path(dir: &str, prefix: &str, extension: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    (1..)
+        .map(|n| Path::new(dir).join(format!("{}{}.{}", prefix, n, extension)))
+        .find(|p| !p.exists())
+        .ok_or_else(|| "No free file name".into())
+}
+
+pub fn save_screenshot(frame: &RgbaImage, scale: u32) -> Result<PathBuf> {
+    let path = next_free_path(SCREENSHOT_DIR, "screenshot", "png")?;
+    scale_frame(frame, scale).save(&path)?;
+    Ok(path)
+}
+
+// GIFs can only have 256 colours in a frame, and the game only uses 16, so the
+// palette can usually be made from the colours exactly. That's much faster than
+// letting the encoder work one out.
+fn gif_frame(frame: RgbaImage) -> Frame<'static> {
+    let (width, height) = (frame.width() as u16, frame.height() as u16);
+
+    let mut colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut palette = Vec::new();
+    let mut indices = Vec::with_capacity(frame.len() / 4);
+
+    for p in frame.pixels() {
+        let color = [p[0], p[1], p[2]];
+        let index = match colors.get(&color) {
+            Some(&i) => i,
+            None if colors.len() < 256 => {
+                let i = colors.len() as u8;
+                colors.insert(color, i);
+                palette.extend_from_slice(&color);
+                i
+            },
+            None => {
+                let mut pixels = frame.into_raw();
+                return Frame::from_rgba_speed(width, height, &mut pixels, GIF_QUANTIZE_SPEED);
+            }
+        };
+
+        indices.push(index);
+    }
+
+    Frame::from_palette_pixels(width, height, &indices, &palette, None)
+}
+
+enum Output {
+    PngSequence(PathBuf),
+    // The encoder needs to know how big the frames are, which depends on how wide
+    // the view is, so it isn't made until the first one arrives.
+    Gif(Option<File>, Option<Encoder<BufWriter<File>>>),
+}
+
+// Writes frames, one per game update, to either an animated GIF or a folder of
+// numbered PNGs. Which one depends on whether the path ends in `.gif`.
+pub struct Recorder {
+    path: PathBuf,
+    scale: u32,
+    frames: usize,
+    output: Output,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, scale: u32) -> Result<Recorder> {
+        let path = path.as_ref().to_owned();
+        let scale = scale.max(1);
+
+        let is_gif = path.extension().map(|e| e.eq_ignore_ascii_case("gif")).unwrap_or(false);
+        let output = if is_gif {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            Output::Gif(Some(File::create(&path)?), None)
+        } else {
+            fs::create_dir_all(&path)?;
+            Output::PngSequence(path.clone())
+        };
+
+        Ok(Recorder {
+            path,
+            scale,
+            frames: 0,
+            output,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn add_frame(&mut self, frame: &RgbaImage) -> Result<()> {
+        let frame = scale_frame(frame, self.scale);
+
+        match &mut self.output {
+            Output::PngSequence(dir) => {
+                frame.save(dir.join(format!("frame{:05}.png", self.frames)))?;
+            },
+            Output::Gif(file, encoder) => {
+                if let Some(file) = file.take() {
+                    let mut new_encoder = Encoder::new(BufWriter::new(file), frame.width() as u16, frame.height() as u16, &[])?;
+                    new_encoder.set(Repeat::Infinite)?;
+                    *encoder = Some(new_encoder);
+                }
+
+                let encoder = encoder.as_mut().ok_or("The GIF encoder has gone missing")?;
+                let mut gif_frame = gif_frame(frame);
+
+                // GIF delays are in hundredths of a second, which doesn't divide
+                // evenly by the update rate, so the delays are spread out to keep
+                // the total right.
+                let ups = UPDATES_PER_SECOND as usize;
+                let delay = ((self.frames + 1) * 100 / ups) - (self.frames * 100 / ups);
+                gif_frame.delay = delay as u16;
+
+                encoder.write_frame(&gif_frame)?;
+            },
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+// The screenshot and recording hotkeys for the game window.
+pub struct Capture {
+    scale: u32,
+    recorder: Option<Recorder>,
+}
+
+impl Capture {
+    pub fn new(scale: u32) -> Capture {
+        Capture {
+            scale,
+            recorder: None,
+        }
+    }
+
+    // Starts recording straight away, to the given path.
+    pub fn record<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.recorder = Some(Recorder::create(path, self.scale)?);
+        Ok(())
+    }
+
+    // The frame is only drawn if it's needed.
+    pub fn handle_button<F: FnOnce() -> RgbaImage>(&mut self, args: ButtonArgs, frame: F) -> Result<()> {
+        let key = match args {
+            ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(key), .. } => key,
+            _ => return Ok(()),
+        };
+
+        match key {
+            Key::F12 => {
+                let path = save_screenshot(&frame(), self.scale)?;
+                println!("Saved screenshot to {}", path.display());
+            },
+            Key::F10 => {
+                match self.recorder.take() {
+                    Some(recorder) => {
+                        println!("Saved {} frames to {}", recorder.frames(), recorder.path().display());
+                    },
+                    None => {
+                        let path = next_free_path(RECORDING_DIR, "recording", "gif")?;
+                        println!("Recording to {}", path.display());
+                        self.record(path)?;
+                    },
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    // Called after every update.
+    pub fn update<F: FnOnce() -> RgbaImage>(&mut self, frame: F) -> Result<()> {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.add_frame(&frame())?;
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
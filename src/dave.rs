@@ -2,12 +2,24 @@ use crate::{
     TILE_SIZE,
     SCALE,
     game::*,
-    assets::*,
-    level::*,
+    tileset::TileId,
     input::*,
 };
 
-#[derive(Debug)]
+// The points around Dave, in pixels from his top left, that are checked against
+// the level every update. Two at the top, two on each side, and two underneath.
+pub const COLLISION_PROBES: [(i16, i16); 8] = [
+    (4,  -1),
+    (10, -1),
+    (12,  4),
+    (12,  12),
+    (10,  16),
+    (4,   16),
+    (3,   12),
+    (3,   4),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum DaveState {
     Live {
         move_type: MovementType,
@@ -30,7 +42,7 @@ pub enum DaveState {
     Dead,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Dave {
     pub position: Position<i8>,
     pub pixel_position: Position<i16>,
@@ -47,6 +59,10 @@ pub struct Dave {
 }
 
 impl Dave {
+    pub fn state(&self) -> &DaveState {
+        &self.state
+    }
+
     pub fn bullet(&self) -> Option<&Bullet> {
         self.bullet.as_ref()
     }
@@ -69,6 +85,13 @@ impl Dave {
         }
     }
 
+    pub fn is_jumping(&self) -> bool {
+        match &self.state {
+            DaveState::Live { move_type: MovementType::Walking { jump, ..}, ..} => *jump == MoveState::Do,
+            _ => false,
+        }
+    }
+
     pub fn is_climbing(&self) -> bool {
         match &self.state {
             DaveState::Live { move_type, ..} => move_type.is_climbing(),
@@ -133,13 +156,13 @@ impl Dave {
         }
     }
 
-    pub fn apply_gravity(&mut self, level: LevelId, assets: &Assets) {
+    pub fn apply_gravity(&mut self, tiles: &[TileId]) {
         if let DaveState::Live {move_type, on_ground, ..} = &mut self.state {
             match &move_type {
                 MovementType::Walking {jump, ..} if *jump != MoveState::Do && !*on_ground => {
                     let is_clear = (
-                        is_clear(level, assets, self.pixel_position + (4, 17)),
-                        is_clear(level, assets, self.pixel_position + (10,17))
+                        is_clear(tiles, self.pixel_position + (4, 17)),
+                        is_clear(tiles, self.pixel_position + (10,17))
                     );
 
                     if is_clear != (CollisionType::Wall, CollisionType::Wall) {
@@ -472,25 +495,14 @@ impl Dave {
         }
     }
 
-    pub fn check_collision(&mut self, level: LevelId, assets: &Assets) {
+    pub fn check_collision(&mut self, tiles: &[TileId]) {
         let mut kill_dave = false;
         match &mut self.state {
             DaveState::Live {collision_point, on_ground, can_climb, ..} => {
-                let offsets = [
-                    (4,  -1),
-                    (10, -1),
-                    (12,  4),
-                    (12,  12),
-                    (10,  16),
-                    (4,   16),
-                    (3,   12),
-                    (3,   4),
-                ];
-
                 let mut local_can_climb = false;
-                for (i, &offset) in offsets.iter().enumerate() {
+                for (i, &offset) in COLLISION_PROBES.iter().enumerate() {
                     let coord = self.pixel_position + offset;
-                    let col_type = is_clear(level, assets, coord);
+                    let col_type = is_clear(tiles, coord);
 
                     use CollisionType::*;
                     collision_point[i] = match col_type {
@@ -534,7 +546,7 @@ impl Dave {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum MovementType {
     Walking {
         jump: MoveState,
@@ -551,7 +563,7 @@ pub enum MovementType {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum HasJetpack {
     No,
     Yes(u8),
@@ -575,7 +587,7 @@ impl MovementType {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum MoveState {
     None,
     Try,
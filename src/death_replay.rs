@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+
+use piston_window::{
+    ButtonArgs, ButtonState, Button,
+    keyboard::Key,
+};
+
+use crate::{
+    UPDATES_PER_SECOND,
+    game::*,
+    level::*,
+    input::Buttons,
+};
+
+// Keeps the last few seconds of play, and when Dave loses a life, shows them again
+// in slow motion with the hitboxes drawn over the top (see Renderer::render_death_replay).
+// Most deaths come down to which grid cell Dave or a monster was in, which is hard
+// to see at full speed.
+//
+// The game restarts Dave in the same update that his dying timer runs out, so the
+// replay starts as soon as he's back, and the restarted game is only shown once
+// it's finished. Return skips it.
+
+// This covers the run up to the death, and the second Dave spends dying.
+pub const DEATH_REPLAY_SECONDS: usize = 4;
+
+// Each update of the replay is shown for this many real updates.
+const SLOW_MOTION: u8 = 4;
+
+struct Frame {
+    snapshot: Snapshot,
+    buttons: Buttons,
+}
+
+struct Playback {
+    frames: Vec<Frame>,
+    frame: usize,
+    wait: u8,
+    game: Game,
+}
+
+pub struct DeathReplay {
+    history: VecDeque<Frame>,
+    capacity: usize,
+    dying: bool,
+    playback: Option<Playback>,
+}
+
+impl Default for DeathReplay {
+    fn default() -> Self {
+        DeathReplay::new(DEATH_REPLAY_SECONDS * UPDATES_PER_SECOND as usize)
+    }
+}
+
+impl DeathReplay {
+    pub fn new(capacity: usize) -> DeathReplay {
+        DeathReplay {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            dying: false,
+            playback: None,
+        }
+    }
+
+    // Called before every update, with the buttons it's about to get.
+    pub fn record(&mut self, game: &Game, buttons: Buttons, levels: &Levels) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(Frame {
+            snapshot: game.snapshot(levels),
+            buttons,
+        });
+        self.dying = !game.dave().is_alive();
+    }
+
+    // Called when the game is wound back an update (see rewind.rs), so the replay
+    // doesn't show updates that were undone.
+    pub fn step_back(&mut self) {
+        self.history.pop_back();
+    }
+
+    // Called after every update. Starts the replay if Dave has just been restarted.
+    pub fn check(&mut self, game: &Game, levels: &Levels) {
+        if !self.dying || !game.dave().is_alive() || self.history.is_empty() {
+            return;
+        }
+
+        let frames: Vec<Frame> = self.history.drain(..).collect();
+        let mut replay_game = game.clone();
+        replay_game.restore(&frames[0].snapshot, levels);
+
+        self.dying = false;
+        self.playback = Some(Playback {
+            frames,
+            frame: 0,
+            wait: SLOW_MOTION,
+            game: replay_game,
+        });
+    }
+
+    // Returns true if the replay was skipped.
+    pub fn handle_button(&mut self, args: ButtonArgs) -> bool {
+        match args {
+            ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(Key::Return), .. } => {
+                self.playback.take().is_some()
+            },
+            _ => false,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    // Moves the replay along, in place of a game update.
+    pub fn update(&mut self, levels: &Levels) {
+        let finished = match &mut self.playback {
+            Some(playback) => {
+                playback.wait -= 1;
+                if playback.wait == 0 {
+                    playback.wait = SLOW_MOTION;
+                    playback.frame += 1;
+
+                    if let Some(frame) = playback.frames.get(playback.frame) {
+                        playback.game.restore(&frame.snapshot, levels);
+                    }
+                }
+
+                playback.frame >= playback.frames.len()
+            },
+            None => false,
+        };
+
+        if finished {
+            self.playback = None;
+        }
+    }
+
+    // The game as it was at the current point of the replay.
+    pub fn game(&self) -> Option<&Game> {
+        self.playback.as_ref().map(|p| &p.game)
+    }
+
+    pub fn title(&self) -> String {
+        match &self.playback {
+            Some(playback) => format!("Dangerous Dave [death replay] {} / {} | {} | Return to skip",
+                playback.frame + 1,
+                playback.frames.len(),
+                playback.frames[playback.frame.min(playback.frames.len() - 1)].buttons,
+            ),
+            None => "Dangerous Dave".to_owned(),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,226 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    Result,
+    game::Position,
+    level::LevelId,
+};
+
+// Every death is appended to a log, kept across sessions, so the death_heatmap
+// tool can show where in each level players are struggling. The log is one death
+// per line: the level (starting at 1), the number of updates since the level was
+// started, Dave's pixel position, and what killed him.
+//
+//  # level tick x y cause
+//  3 1042 368 80 hazard
+//  3 1337 96 128 monster
+pub const DEATH_LOG_FILE: &str = "deaths.txt";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeathCause {
+    // Fire, water, weeds, and the rest of the deadly tiles.
+    Hazard,
+    // Walking into a monster.
+    Monster,
+    MonsterBullet,
+}
+
+const CAUSE_NAMES: [(&str, DeathCause); 3] = [
+    ("hazard", DeathCause::Hazard),
+    ("monster", DeathCause::Monster),
+    ("bullet", DeathCause::MonsterBullet),
+];
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, _) = CAUSE_NAMES.iter()
+            .find(|(_, c)| c == self)
+            .unwrap();
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DeathCause {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<DeathCause, String> {
+        CAUSE_NAMES.iter()
+            .find(|(name, _)| *name == s)
+            .map(|&(_, c)| c)
+            .ok_or_else(|| format!("Invalid death cause: {}", s))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DeathEvent {
+    pub level: LevelId,
+    pub tick: u32,
+    pub position: Position<i16>,
+    pub cause: DeathCause,
+}
+
+impl fmt::Display for DeathEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {} {}",
+            self.level.val() + 1,
+            self.tick,
+            self.position.x,
+            self.position.y,
+            self.cause
+        )
+    }
+}
+
+impl FromStr for DeathEvent {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<DeathEvent> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        if parts.len() != 5 {
+            Err(format!("Invalid death event: {}", s))?;
+        }
+
+        let level: usize = parts[0].parse()?;
+        Ok(DeathEvent {
+            level: LevelId::new(level.wrapping_sub(1))?,
+            tick: parts[1].parse()?,
+            position: Position {
+                x: parts[2].parse()?,
+                y: parts[3].parse()?,
+            },
+            cause: parts[4].parse()?,
+        })
+    }
+}
+
+// Opens the log for every write, so nothing is lost if the game is closed without
+// warning.
+pub fn append_death<P: AsRef<Path>>(path: P, death: &DeathEvent) -> Result<()> {
+    let path = path.as_ref();
+    let is_new = !path.exists();
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    if is_new {
+        writeln!(file, "# level tick x y cause")?;
+    }
+
+    writeln!(file, "{}", death)?;
+    Ok(())
+}
+
+pub fn load_deaths<P: AsRef<Path>>(path: P) -> Result<Vec<DeathEvent>> {
+    let file = BufReader::new(File::open(path)?);
+
+    let mut deaths = Vec::new();
+    for (line_num, line) in file.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let death = line.parse()
+            .map_err(|e| format!("Invalid death log on line {}: {}", line_num + 1, e))?;
+        deaths.push(death);
+    }
+
+    Ok(deaths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, process};
+
+    fn death(level: usize, tick: u32, x: i16, y: i16, cause: DeathCause) -> DeathEvent {
+        DeathEvent {
+            level: LevelId::new(level).unwrap(),
+            tick,
+            position: Position { x, y },
+            cause,
+        }
+    }
+
+    fn assert_same(a: &DeathEvent, b: &DeathEvent) {
+        assert_eq!((a.level, a.tick, a.position, a.cause), (b.level, b.tick, b.position, b.cause));
+    }
+
+    #[test]
+    fn save_then_load_event() {
+        let deaths = [
+            death(2, 1042, 368, 80, DeathCause::Hazard),
+            death(0, 0, 0, 0, DeathCause::Monster),
+            death(9, u32::MAX, -4, 1600, DeathCause::MonsterBullet),
+        ];
+
+        for d in deaths.iter() {
+            let line = d.to_string();
+            assert_same(&line.parse().unwrap(), d);
+        }
+
+        // Levels are written counting from 1.
+        assert_eq!(deaths[0].to_string(), "3 1042 368 80 hazard");
+    }
+
+    #[test]
+    fn load_rejects_bad_events() {
+        let bad = [
+            // Unknown causes, which are case sensitive.
+            "3 1042 368 80 lava",
+            "3 1042 368 80 Hazard",
+            // Cut short, or with too much.
+            "3 1042 368 80",
+            "3 1042",
+            "",
+            "3 1042 368 80 hazard 1",
+            // Out of range, or not numbers.
+            "0 1042 368 80 hazard",
+            "11 1042 368 80 hazard",
+            "3 -1 368 80 hazard",
+            "3 1042 x 80 hazard",
+        ];
+
+        for line in bad.iter() {
+            assert!(line.parse::<DeathEvent>().is_err(), "{:?} should fail to parse", line);
+        }
+    }
+
+    #[test]
+    fn append_then_load_log() {
+        let path = env::temp_dir().join(format!("ddave_deaths_test_{}.txt", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let deaths = [
+            death(2, 1042, 368, 80, DeathCause::Hazard),
+            death(2, 1337, 96, 128, DeathCause::Monster),
+        ];
+        for d in deaths.iter() {
+            append_death(&path, d).unwrap();
+        }
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let loaded = load_deaths(&path);
+        fs::remove_file(&path).unwrap();
+
+        // The header's only written once, when the log's started.
+        assert_eq!(contents, "# level tick x y cause\n3 1042 368 80 hazard\n3 1337 96 128 monster\n");
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), deaths.len());
+        for (a, b) in loaded.iter().zip(deaths.iter()) {
+            assert_same(a, b);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+use piston_window::{
+    ButtonArgs, ButtonState, Button, MouseButton,
+    keyboard::Key,
+};
+
+use crate::{
+    TILE_SIZE,
+    game::*,
+    level::Levels,
+    tileset::TileId,
+    dave::{DaveState, COLLISION_PROBES},
+    renderer::{Renderer, RenderBackend},
+    framebuffer::screen_width,
+    text::*,
+};
+
+// An overlay showing what the game is thinking, drawn over the level.
+//
+//  Tab         Show or hide the overlay.
+//  Left click  Show the id and attributes of the tile under the mouse.
+//
+// It draws the tile grid, Dave's collision probes (green if the last collision
+// check let him through there, red if not), the grid cells that Dave, the monsters
+// and the bullets are in, the rest of each monster's path, and the game's own view
+// of the level, with where it's scrolling to if it's on the move. The panel in the
+// corner has the details.
+
+const GRID_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const VIEW_COLOR: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+const SCROLL_COLOR: [f32; 4] = [0.0, 0.5, 1.0, 1.0];
+const PATH_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+const PANEL_WIDTH: u32 = 200;
+
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    enabled: bool,
+    // The grid cell that was clicked on.
+    selected: Option<Position<i16>>,
+}
+
+impl DebugOverlay {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn handle_button(&mut self, args: ButtonArgs) {
+        if let ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(Key::Tab), .. } = args {
+            self.enabled = !self.enabled;
+        }
+    }
+
+    // Whether the button is a click that should pick a tile, if the overlay is up.
+    pub fn is_click(&self, args: ButtonArgs) -> bool {
+        self.enabled && matches!(args, ButtonArgs {
+            state: ButtonState::Press,
+            button: Button::Mouse(MouseButton::Left),
+            ..
+        })
+    }
+
+    // Takes a pixel position in the level, which is None if the click missed it.
+    pub fn select(&mut self, pixel: Option<Position<i16>>) {
+        self.selected = pixel.map(|p| Position {
+            x: p.x / TILE_SIZE as i16,
+            y: p.y / TILE_SIZE as i16,
+        });
+    }
+
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, game: &Game, levels: &Levels, view_x: f64) {
+        if !self.enabled {
+            return;
+        }
+
+        let tile = TILE_SIZE as f64;
+        let to_screen = |pos: Position<i16>| Renderer::to_screen(view_x, pos);
+        let outline_cell = |b: &mut B, cell: Position<i16>, color| {
+            let (x, y) = to_screen(Position { x: cell.x * TILE_SIZE as i16, y: cell.y * TILE_SIZE as i16 });
+            b.outline_rect(color, [x, y, tile, tile]);
+        };
+        let cell_of = |pos: Position<i16>| Position {
+            x: pos.x / TILE_SIZE as i16,
+            y: pos.y / TILE_SIZE as i16,
+        };
+
+        // The level is 10 tiles high, and starts under the top of the HUD.
+        let width = screen_width(game) as f64;
+        let (top, bottom) = (tile, 11.0 * tile);
+        let first_line = (tile - view_x % tile) % tile;
+        for i in 0..=(width / tile) as u32 {
+            let x = first_line + i as f64 * tile;
+            b.draw_line(GRID_COLOR, [x, top], [x, bottom]);
+        }
+        for i in 0..=10 {
+            let y = top + i as f64 * tile;
+            b.draw_line(GRID_COLOR, [0.0, y], [width, y]);
+        }
+
+        // The game's view can be different to what's on screen with the smooth camera,
+        // or when it's part way through scrolling.
+        let view_left = (game.view_x() as i16 * TILE_SIZE as i16) as f64 - view_x;
+        let view_width = game.view_width() as f64 * tile;
+        b.outline_rect(VIEW_COLOR, [view_left, top, view_width, bottom - top]);
+        if game.scroll_x() != 0 {
+            let scroll_left = view_left + game.scroll_x() as f64 * tile;
+            b.outline_rect(SCROLL_COLOR, [scroll_left, top + 1.0, view_width, bottom - top - 2.0]);
+        }
+
+        let path = levels[game.current_level()].path();
+        for monster in game.monsters().iter().filter(|m| m.is_alive()) {
+            let pos = monster.position();
+            outline_cell(b, Position { x: pos.x as i16, y: pos.y as i16 }, [1.0, 0.0, 0.0, 1.0]);
+
+            // Drawn through the middle of the monster.
+            let points: Vec<(f64, f64)> = monster.remaining_path(path).into_iter()
+                .map(|p| to_screen(p + (TILE_SIZE as i16 / 2, TILE_SIZE as i16 / 2)))
+                .collect();
+            for pair in points.windows(2) {
+                b.draw_line(PATH_COLOR, [pair[0].0, pair[0].1], [pair[1].0, pair[1].1]);
+            }
+        }
+
+        let bullets = game.dave().bullet().into_iter()
+            .map(|b| (b, [1.0, 1.0, 0.0, 1.0]))
+            .chain(game.monster_bullet().map(|b| (b, [1.0, 0.5, 0.0, 1.0])));
+        for (bullet, color) in bullets {
+            outline_cell(b, cell_of(bullet.position), color);
+            let (x, y) = to_screen(bullet.position);
+            b.fill_rect(color, [x - 1.0, y - 1.0, 3.0, 3.0]);
+        }
+
+        let dave = game.dave();
+        outline_cell(b, cell_of(dave.pixel_position), [1.0, 1.0, 0.0, 1.0]);
+
+        if let DaveState::Live { collision_point, .. } = dave.state() {
+            for (&offset, &clear) in COLLISION_PROBES.iter().zip(collision_point.iter()) {
+                let color = if clear { [0.0, 1.0, 0.0, 1.0] } else { [1.0, 0.0, 0.0, 1.0] };
+                let (x, y) = to_screen(dave.pixel_position + offset);
+                b.fill_rect(color, [x, y, 1.0, 1.0]);
+            }
+        }
+
+        if let Some(cell) = self.selected {
+            outline_cell(b, cell, SELECTED_COLOR);
+        }
+
+        self.draw_panel(b, game);
+    }
+
+    fn draw_panel<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        let dave = game.dave();
+
+        let mut lines = vec![
+            format!("view {} of {} scroll {}", game.view_x(), game.view_width(), game.scroll_x()),
+            format!("dave {},{} cell {},{}",
+                dave.pixel_position.x, dave.pixel_position.y, dave.position.x, dave.position.y),
+        ];
+
+        match dave.state() {
+            DaveState::Live { move_type, on_ground, can_climb, collision_point, .. } => {
+                let probes: String = collision_point.iter()
+                    .map(|&clear| if clear { '1' } else { '0' })
+                    .collect();
+
+                lines.push(format!("live ground {} climb {} probes {}", on_ground, can_climb, probes));
+                lines.push(format!("{:?}", move_type));
+            },
+            DaveState::Dying { dead_timer } => lines.push(format!("dying {}", dead_timer)),
+            DaveState::Dead => lines.push("dead".to_owned()),
+        }
+
+        if let Some(cell) = self.selected {
+            let id = game.tiles()[cell.y as usize * 100 + cell.x as usize];
+            lines.push(format!("tile {},{} id {}: {}", cell.x, cell.y, id.val(), tile_attributes(id)));
+        }
+
+        let text = Text::new().wrap(PANEL_WIDTH);
+        let body = lines.join("\n");
+        let (_, height) = text.size(&body);
+
+        let (x, y) = (2.0, TILE_SIZE as f64 + 2.0);
+        b.fill_rect(PANEL_COLOR, [x, y, (PANEL_WIDTH + 4) as f64, (height + 4) as f64]);
+        text.draw(b, &body, x + 2.0, y + 2.0);
+    }
+}
+
+fn tile_attributes(id: TileId) -> String {
+    let attributes: Vec<&str> = [
+        (id.is_collidable(), "wall"),
+        (id.is_hazard(), "hazard"),
+        (id.is_pickup(), "pickup"),
+        (id.is_gem(), "gem"),
+        (id.is_trophy(), "trophy"),
+        (id.is_door(), "door"),
+        (id.is_climbable(), "climbable"),
+    ].iter()
+        .filter(|(has, _)| *has)
+        .map(|&(_, name)| name)
+        .collect();
+
+    if attributes.is_empty() {
+        "empty".to_owned()
+    } else {
+        attributes.join(" ")
+    }
+}
\ No newline at end of file
@@ -0,0 +1,83 @@
+use crate::framebuffer::SCREEN_HEIGHT;
+
+// How the game's screen is fitted into the window. It's scaled up as far as
+// it'll go while keeping its shape, and centred, with black bars filling whatever
+// is left over.
+
+// The original ran 320x200 on 4:3 monitors, so its pixels were 20% taller than they
+// were wide.
+const ASPECT_CORRECTION: f64 = 1.2;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DisplaySettings {
+    // Only scale by whole numbers, so every pixel is the same size.
+    pub integer_scaling: bool,
+    // Stretch the pixels to the original 4:3 shape, rather than keeping them square.
+    pub aspect_correction: bool,
+}
+
+// Where the game's screen goes in the window, and how much it's scaled by.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    // In the game's pixels, as it depends on how wide the view is.
+    pub screen_width: u32,
+    pub x: f64,
+    pub y: f64,
+    pub scale_x: f64,
+    pub scale_y: f64,
+}
+
+impl Layout {
+    pub fn width(&self) -> f64 {
+        self.screen_width as f64 * self.scale_x
+    }
+
+    pub fn height(&self) -> f64 {
+        SCREEN_HEIGHT as f64 * self.scale_y
+    }
+}
+
+impl DisplaySettings {
+    fn pixel_aspect(&self) -> f64 {
+        if self.aspect_correction {
+            ASPECT_CORRECTION
+        } else {
+            1.0
+        }
+    }
+
+    pub fn layout(&self, screen_width: u32, window_width: f64, window_height: f64) -> Layout {
+        let aspect = self.pixel_aspect();
+
+        let mut scale = (window_width / screen_width as f64)
+            .min(window_height / (SCREEN_HEIGHT as f64 * aspect));
+
+        // Whole numbers only, and never smaller than the original, even if it
+        // doesn't fit.
+        if self.integer_scaling {
+            scale = scale.floor().max(1.0);
+        }
+
+        let layout = Layout {
+            screen_width,
+            x: 0.0,
+            y: 0.0,
+            scale_x: scale,
+            scale_y: scale * aspect,
+        };
+
+        Layout {
+            x: ((window_width - layout.width()) / 2.0).floor(),
+            y: ((window_height - layout.height()) / 2.0).floor(),
+            ..layout
+        }
+    }
+
+    // The size of window that fits the screen exactly at the given scale.
+    pub fn window_size(&self, screen_width: u32, scale: u32) -> [u32; 2] {
+        [
+            screen_width * scale,
+            (SCREEN_HEIGHT as f64 * scale as f64 * self.pixel_aspect()).round() as u32,
+        ]
+    }
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+use image::{RgbImage, DynamicImage};
+
+use crate::{
+    Result,
+    game::*,
+    level::*,
+    input::{Input, Buttons},
+    dave::HasJetpack,
+    tileset::TileImages,
+    renderer::Renderer,
+    framebuffer::*,
+};
+
+// Finishing a level is worth more than anything that can be picked up in it, so
+// agents aren't tempted to farm points instead of heading for the door.
+pub const LEVEL_REWARD: f32 = 5000.0;
+
+// Everything an agent can see of the game, without having to scrape the screen.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub level: LevelId,
+    // The level's 100x10 tile ids, row by row, including any pickups already taken.
+    pub tiles: Vec<u8>,
+    pub dave: Position<i16>,
+    // Pixel positions of the monsters still alive.
+    pub monsters: Vec<Position<i16>>,
+    pub has_trophy: bool,
+    pub has_gun: bool,
+    pub jetpack_fuel: u8,
+    pub score: u32,
+    pub lives: u8,
+}
+
+pub trait Environment {
+    // The game has no randomness, so the seed only exists to keep the interface
+    // the same as other environments.
+    fn reset(&mut self, level: LevelId, seed: u64) -> Observation;
+
+    // Runs a single game update with the given buttons held, returning the new
+    // observation, the reward for the step, and whether the episode is over.
+    fn step(&mut self, action: Buttons) -> (Observation, f32, bool);
+
+    fn render_rgb(&self) -> Option<RgbImage> {
+        None
+    }
+}
+
+// An episode covers a single level, and ends when Dave goes through the door or
+// runs out of lives.
+pub struct DaveEnv {
+    levels: Levels,
+    game: Game,
+    // Only needed for rendering, which most agents can do without.
+    tiles: Option<TileImages>,
+    renderer: Renderer,
+}
+
+impl DaveEnv {
+    pub fn new() -> Result<DaveEnv> {
+        Ok(DaveEnv::with_levels(load_levels()?))
+    }
+
+    pub fn with_levels(levels: Levels) -> DaveEnv {
+        DaveEnv {
+            game: Game::init_level(LevelId::first_level(), &levels),
+            levels,
+            tiles: None,
+            renderer: Renderer::new(),
+        }
+    }
+
+    // Lets render_rgb draw the game, using the tiles from `load_tile_images`.
+    pub fn with_tiles(mut self, tiles: TileImages) -> DaveEnv {
+        self.tiles = Some(tiles);
+        self
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn levels(&self) -> &Levels {
+        &self.levels
+    }
+
+    fn observe(&self) -> Observation {
+        let game = &self.game;
+        let level = game.current_level();
+
+        Observation {
+            level,
+            tiles: game.tiles().iter().map(|t| t.val()).collect(),
+            dave: game.dave().pixel_position,
+            monsters: game.monsters().iter()
+                .filter(|m| m.is_alive())
+                .map(|m| m.pixel_position())
+                .collect(),
+            has_trophy: game.has_trophy(),
+            has_gun: game.has_gun(),
+            jetpack_fuel: match game.has_jetpack() {
+                HasJetpack::Yes(fuel) => fuel,
+                HasJetpack::No => 0,
+            },
+            score: game.score(),
+            lives: game.lives(),
+        }
+    }
+}
+
+impl Environment for DaveEnv {
+    fn reset(&mut self, level: LevelId, _seed: u64) -> Observation {
+        self.game = Game::init_level(level, &self.levels);
+        self.observe()
+    }
+
+    fn step(&mut self, action: Buttons) -> (Observation, f32, bool) {
+        let level = self.game.current_level();
+        let score = self.game.score();
+
+        self.game.input(&Input::from_buttons(action));
+        self.game.update(&self.levels);
+        // Nobody is listening, so don't let them pile up.
+        self.game.drain_sounds();
+        self.renderer.update(&self.game);
+
+        // Winning the last level quits the game without changing level, but leaves
+        // Dave alive, unlike running out of lives.
+        let completed = self.game.current_level() != level
+            || (self.game.quit() && self.game.dave().is_alive());
+
+        let mut reward = (self.game.score() - score) as f32;
+        if completed {
+            reward += LEVEL_REWARD;
+        }
+
+        (self.observe(), reward, completed || self.game.quit())
+    }
+
+    fn render_rgb(&self) -> Option<RgbImage> {
+        let tiles = self.tiles.as_ref()?;
+
+        let mut framebuffer = Framebuffer::new(tiles, screen_width(&self.game));
+        self.renderer.draw(&mut framebuffer, &self.game);
+        Some(DynamicImage::ImageRgba8(framebuffer.into_image()).to_rgb())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,118 @@
+use image::{RgbaImage, Rgba};
+
+use crate::{
+    TILE_SIZE,
+    renderer::RenderBackend,
+    tileset::*,
+    game::Game,
+};
+
+pub const SCREEN_WIDTH: u32 = 320;
+pub const SCREEN_HEIGHT: u32 = 200;
+
+// The classic screen is 320 wide, but a wider view (see Game::with_view_width) makes
+// for a wider screen. It's always the same height.
+pub fn screen_width(game: &Game) -> u32 {
+    game.view_width() as u32 * TILE_SIZE
+}
+
+// A render backend that composites the game's screen from the tile images on the
+// CPU, with no window or GPU needed. The result is an ordinary image, at the
+// game's own resolution, to be saved or handed to something else.
+pub struct Framebuffer<'a> {
+    tiles: &'a TileImages,
+    image: RgbaImage,
+}
+
+impl<'a> Framebuffer<'a> {
+    pub fn new(tiles: &'a TileImages, width: u32) -> Framebuffer<'a> {
+        Framebuffer {
+            tiles,
+            image: RgbaImage::new(width, SCREEN_HEIGHT),
+        }
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+
+    // Draws a pixel over whatever is already there, mixing them by its alpha.
+    fn blend(&mut self, x: i32, y: i32, color: Rgba<u8>) {
+        if x < 0 || y < 0 || x >= self.image.width() as i32 || y >= self.image.height() as i32 {
+            return;
+        }
+
+        let alpha = color[3] as u32;
+        if alpha == 0 {
+            return;
+        }
+
+        let dest = self.image.get_pixel_mut(x as u32, y as u32);
+        for i in 0..3 {
+            dest[i] = ((color[i] as u32 * alpha + dest[i] as u32 * (255 - alpha)) / 255) as u8;
+        }
+        dest[3] = 255;
+    }
+}
+
+fn to_rgba(color: [f32; 4]) -> Rgba<u8> {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgba([channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])])
+}
+
+impl<'a> RenderBackend for Framebuffer<'a> {
+    fn clear(&mut self, color: [f32; 4]) {
+        let color = to_rgba(color);
+        for p in self.image.pixels_mut() {
+            *p = color;
+        }
+    }
+
+    fn draw_tile(&mut self, tile: TileId, x: f64, y: f64) {
+        let tiles = self.tiles;
+        let (x, y) = (x.floor() as i32, y.floor() as i32);
+
+        for (tx, ty, &p) in tiles[tile].enumerate_pixels() {
+            self.blend(x + tx as i32, y + ty as i32, p);
+        }
+    }
+
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        let color = to_rgba(color);
+        let (x0, y0) = (rect[0].floor() as i32, rect[1].floor() as i32);
+        let (x1, y1) = ((rect[0] + rect[2]).floor() as i32, (rect[1] + rect[3]).floor() as i32);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    fn outline_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        let [x, y, w, h] = rect;
+        self.fill_rect(color, [x, y, w, 1.0]);
+        self.fill_rect(color, [x, y + h - 1.0, w, 1.0]);
+        self.fill_rect(color, [x, y + 1.0, 1.0, h - 2.0]);
+        self.fill_rect(color, [x + w - 1.0, y + 1.0, 1.0, h - 2.0]);
+    }
+
+    // A pixel wide, one pixel at a time along the longer side.
+    fn draw_line(&mut self, color: [f32; 4], from: [f64; 2], to: [f64; 2]) {
+        let color = to_rgba(color);
+        let (x0, y0) = (from[0].floor(), from[1].floor());
+        let (x1, y1) = (to[0].floor(), to[1].floor());
+
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1.0);
+        for i in 0..=steps as i32 {
+            let t = i as f64 / steps;
+            let x = (x0 + (x1 - x0) * t).round() as i32;
+            let y = (y0 + (y1 - y0) * t).round() as i32;
+            self.blend(x, y, color);
+        }
+    }
+}
\ No newline at end of file
@@ -1,26 +1,74 @@
-use std::ops::Add;
+use std::{
+    ops::Add,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use crate::{
     level::*, Result, TILE_SIZE,
-    assets::Assets,
     input::Input,
     tileset::*,
     dave::*,
     monster::*,
+    audio::SoundEffect,
+    deaths::{DeathEvent, DeathCause},
 };
 
+// The original shows 20 tiles of the level at a time, out of the 100 it's made of.
+pub const CLASSIC_VIEW_WIDTH: u8 = 20;
+const LEVEL_WIDTH: u8 = 100;
+
+// Every change to a game's tiles gives it a new stamp, unique across all games, so
+// two games with the same stamp have the same tiles. The renderer uses it to tell
+// when a level needs redrawing (see layer.rs), without going through the tiles.
+static NEXT_TILES_STAMP: AtomicU64 = AtomicU64::new(1);
+
+fn new_tiles_stamp() -> u64 {
+    NEXT_TILES_STAMP.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Clone)]
 pub struct Game {
+    // Picking up items changes the level, so each game keeps its own copy of the
+    // current level's tiles. Everything else in the level data is read-only, and
+    // shared between games.
+    tiles: [TileId; 1000],
+    tiles_stamp: u64,
+    dave: Dave,
+    misc: MiscParts,
+    monsters: [Monster; 5],
+    monster_bullet: Option<Bullet>,
+    sounds: Vec<SoundEffect>,
+    death: Option<DeathEvent>,
+}
+
+// A copy of a game, without the full copy of the level's tiles that makes up most
+// of it. Only the tiles that differ from the level data are kept, which is just
+// the pickups that have been taken.
+#[derive(Clone)]
+pub struct Snapshot {
+    changed_tiles: Vec<(u16, TileId)>,
+    tiles_stamp: u64,
     dave: Dave,
     misc: MiscParts,
     monsters: [Monster; 5],
     monster_bullet: Option<Bullet>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct MiscParts {
     level: LevelId,
     view_x: i8,
+    // How many tiles across the view is. Monsters only shoot, and bullets only
+    // fly, while they're in view, so this changes how the game plays, not just
+    // how it looks.
+    view_width: u8,
     scroll_x: i16,
+    // Where the smooth camera has the screen, in pixels from the left of the level,
+    // when it's on (see camera.rs). The screen isn't the game's own view then, so
+    // monsters and bullets go by what's on it instead.
+    camera_x: Option<i16>,
+    // Updates since the level was started.
+    tick: u32,
     score: u32,
     lives: u8,
     has_trophy: bool,
@@ -56,6 +104,32 @@ impl Game {
         self.misc.view_x
     }
 
+    // How many more tiles the view has left to scroll, and which way.
+    pub fn scroll_x(&self) -> i16 {
+        self.misc.scroll_x
+    }
+
+    pub fn view_width(&self) -> u8 {
+        self.misc.view_width
+    }
+
+    pub fn camera_x(&self) -> Option<i16> {
+        self.misc.camera_x
+    }
+
+    // Called before every update with where the smooth camera is, if it's on.
+    pub fn set_camera_x(&mut self, camera_x: Option<i16>) {
+        self.misc.camera_x = camera_x;
+    }
+
+    pub fn tiles(&self) -> &[TileId] {
+        &self.tiles
+    }
+
+    pub fn tiles_stamp(&self) -> u64 {
+        self.tiles_stamp
+    }
+
     pub fn dave(&self) -> &Dave {
         &self.dave
     }
@@ -71,15 +145,35 @@ impl Game {
     pub fn monster_bullet(&self) -> Option<&Bullet> {
         self.monster_bullet.as_ref()
     }
+
+    pub fn drain_sounds(&mut self) -> std::vec::Drain<'_, SoundEffect> {
+        self.sounds.drain(..)
+    }
+
+    // Dave can only die once before he's restarted, so there's only ever one of
+    // these waiting.
+    pub fn take_death(&mut self) -> Option<DeathEvent> {
+        self.death.take()
+    }
 }
 
 impl Game {
-    pub fn init() -> Result<Self> {
+    pub fn init(levels: &Levels) -> Result<Self> {
+        Ok(Game::init_level(LevelId::first_level(), levels))
+    }
+
+    // Starts a new game at the given level, rather than the first.
+    pub fn init_level(level: LevelId, levels: &Levels) -> Self {
         let mut game = Game {
+            tiles: [TileId::TILE_BLANK; 1000],
+            tiles_stamp: new_tiles_stamp(),
             misc: MiscParts {
-                level: LevelId::first_level(),
+                level,
                 view_x: 0,
+                view_width: CLASSIC_VIEW_WIDTH,
                 scroll_x: 0,
+                camera_x: None,
+                tick: 0,
                 score: 0,
                 lives: 3,
                 has_trophy: false,
@@ -89,22 +183,71 @@ impl Game {
             dave: Dave::init(),
             monsters: [Monster::init_dead(), Monster::init_dead(), Monster::init_dead(), Monster::init_dead(), Monster::init_dead()],
             monster_bullet: None,
+            sounds: Vec::new(),
+            death: None,
         };
 
-        game.start_level();
+        game.start_level(levels);
+
+        game
+    }
+
+    // Anything from the classic 20 tiles up to the whole level.
+    pub fn with_view_width(mut self, width: u8) -> Self {
+        self.misc.view_width = width.clamp(CLASSIC_VIEW_WIDTH, LEVEL_WIDTH);
+        self.misc.view_x = self.misc.view_x.min(self.max_view_x());
+        self
+    }
+
+    fn max_view_x(&self) -> i8 {
+        (LEVEL_WIDTH - self.misc.view_width) as i8
+    }
+
+    pub fn snapshot(&self, levels: &Levels) -> Snapshot {
+        let level_tiles = levels[self.misc.level].tiles();
+
+        Snapshot {
+            changed_tiles: self.tiles.iter()
+                .zip(level_tiles.iter())
+                .enumerate()
+                .filter(|(_, (t, l))| t != l)
+                .map(|(i, (&t, _))| (i as u16, t))
+                .collect(),
+            tiles_stamp: self.tiles_stamp,
+            dave: self.dave.clone(),
+            misc: self.misc.clone(),
+            monsters: self.monsters.clone(),
+            monster_bullet: self.monster_bullet.clone(),
+        }
+    }
 
-        Ok(game)
+    pub fn restore(&mut self, snapshot: &Snapshot, levels: &Levels) {
+        self.tiles.copy_from_slice(levels[snapshot.misc.level].tiles());
+        for &(i, t) in snapshot.changed_tiles.iter() {
+            self.tiles[i as usize] = t;
+        }
+        self.tiles_stamp = snapshot.tiles_stamp;
+
+        self.dave = snapshot.dave.clone();
+        self.misc = snapshot.misc.clone();
+        self.monsters = snapshot.monsters.clone();
+        self.monster_bullet = snapshot.monster_bullet.clone();
+        self.sounds.clear();
+        self.death = None;
     }
 
     fn scroll_screen(&mut self) {
+        // The view scrolls by 15 tiles once Dave is within 2 of either edge, which
+        // is most of a screen at the classic width.
+        let right_edge = self.misc.view_width as i16 - 2;
         match self.dave.position.x as i16 - self.misc.view_x as i16 {
-            18 ..= 255 => self.misc.scroll_x = 15,
-            -255 ..= 1    => self.misc.scroll_x = -15,
+            x if x >= right_edge => self.misc.scroll_x = 15,
+            x if x <= 1 => self.misc.scroll_x = -15,
             _ => {}
         }
 
         if self.misc.scroll_x > 0 {
-            self.misc.view_x = (self.misc.view_x + 1).min(80);
+            self.misc.view_x = (self.misc.view_x + 1).min(self.max_view_x());
             self.misc.scroll_x -= 1;
         }
 
@@ -114,21 +257,22 @@ impl Game {
         }
     }
 
-    fn pickup_item(&mut self, assets: &mut Assets) {
+    fn pickup_item(&mut self) {
         if self.dave.check_pickup.x == 0 && self.dave.check_pickup.y == 0 {
             return;
         }
 
-        let level = assets.get_level_mut(self.misc.level);
-        let tile_type = &mut level.tiles_mut()[self.dave.check_pickup.y as usize * 100 + self.dave.check_pickup.x as usize];
+        let tile_index = self.dave.check_pickup.y as usize * 100 + self.dave.check_pickup.x as usize;
+        let tile_type = self.tiles[tile_index];
 
-        match *tile_type {
+        match tile_type {
             // Add score and special item cases here later.
             TileId::TILE_JETPACK => self.dave.has_jetpack = HasJetpack::Yes(255),
             TileId::TILE_GUN => self.dave.has_gun = true,
             t if t.is_trophy() => {
                 self.add_score(1000);
                 self.misc.has_trophy = true;
+                self.sounds.push(SoundEffect::Trophy);
             },
 
             TileId::TILE_SCORE_BLUE_GEM => self.add_score(100),
@@ -140,53 +284,64 @@ impl Game {
             _ => {}
         }
 
-        *tile_type = TileId::TILE_BLANK;
+        if tile_type.is_gem() {
+            self.sounds.push(SoundEffect::Gem);
+        }
+
+        self.tiles[tile_index] = TileId::TILE_BLANK;
+        self.tiles_stamp = new_tiles_stamp();
         self.dave.check_pickup = Default::default();
     }
 
-    fn start_level(&mut self) {
+    fn start_level(&mut self, levels: &Levels) {
         let start_pos = self.misc.level.start_position();
 
+        self.tiles.copy_from_slice(levels[self.misc.level].tiles());
+        self.tiles_stamp = new_tiles_stamp();
+
         self.dave.new_level(start_pos);
 
         self.monsters = self.misc.level.monsters();
 
         self.misc.view_x = 0;
+        self.misc.tick = 0;
         self.misc.has_trophy = false;
     }
 
-    fn update_bullets(&mut self, assets: &Assets) {
+    fn update_bullets(&mut self) {
         let dave_pos = self.dave.position;
         if let Some(bullet) = self.dave.bullet_mut() {
-            match bullet.update_bullet(dave_pos, &self.monsters, &self.misc, assets) {
+            match bullet.update_bullet(dave_pos, &self.monsters, &self.misc, &self.tiles) {
                 (CollisionType::Wall, _) | (_, false) => *self.dave.bullet_mut() = None,
                 (CollisionType::Monster(id), _) => {
                     *self.dave.bullet_mut() = None;
                     self.monsters[id].kill();
+                    self.sounds.push(SoundEffect::MonsterDeath);
                 }
                 _ => {},
             }
         }
 
         if let Some(bullet) = &mut self.monster_bullet {
-            match bullet.update_bullet(dave_pos, &self.monsters, &self.misc, assets) {
+            match bullet.update_bullet(dave_pos, &self.monsters, &self.misc, &self.tiles) {
                 (CollisionType::Wall, _) | (_, false) => self.monster_bullet = None,
                 (CollisionType::Dave, _) if self.dave.is_alive() => {
                     self.monster_bullet = None;
-                    self.dave.kill();
+                    self.kill_dave(DeathCause::MonsterBullet);
                 }
                 _ => {},
             }
         }
     }
 
-    fn update_level(&mut self) {
+    fn update_level(&mut self, levels: &Levels) {
         if self.dave.check_door {
             if self.misc.has_trophy {
                 self.add_score(2000);
+                self.sounds.push(SoundEffect::Door);
                 if let Some(next) = self.misc.level.next() {
                     self.misc.level = next;
-                    self.start_level();
+                    self.start_level(levels);
                 } else {
                     println!("You won with {} points!", self.misc.score);
                     self.misc.quit = true;
@@ -206,46 +361,91 @@ impl Game {
         }
 
         if self.dave.is_alive() {
-            for m in self.monsters.iter_mut().filter(|m| m.is_alive()) {
-                if self.dave.position == m.position() {
-                    self.dave.kill();
-                    m.kill();
-                    break;
-                }
+            let dave_pos = self.dave.position;
+            if let Some(m) = self.monsters.iter_mut().find(|m| m.is_alive() && m.position() == dave_pos) {
+                m.kill();
+                self.kill_dave(DeathCause::Monster);
+                self.sounds.push(SoundEffect::MonsterDeath);
             }
         }
     }
 
+    fn kill_dave(&mut self, cause: DeathCause) {
+        self.dave.kill();
+        self.record_death(cause);
+    }
+
+    fn record_death(&mut self, cause: DeathCause) {
+        self.death = Some(DeathEvent {
+            level: self.misc.level,
+            tick: self.misc.tick,
+            position: self.dave.pixel_position,
+            cause,
+        });
+    }
+
     fn add_score(&mut self, new_score: u32) {
         if self.misc.score / 20_000 != (self.misc.score + new_score) / 20_000 {
             self.misc.lives += 1;
+            self.sounds.push(SoundEffect::ExtraLife);
         }
 
         self.misc.score += new_score;
     }
 
-    pub fn update(&mut self, assets: &mut Assets) {
-        self.dave.check_collision(self.misc.level, assets);
-        self.pickup_item(assets);
+    pub fn update(&mut self, levels: &Levels) {
+        let was_alive = self.dave.is_alive();
+        let was_jumping = self.dave.is_jumping();
+        let had_bullet = self.dave.bullet().is_some();
+
+        self.misc.tick += 1;
+
+        // Hazards are found by Dave himself, so the only way to know is to look.
+        self.dave.check_collision(&self.tiles);
+        if was_alive && !self.dave.is_alive() {
+            self.record_death(DeathCause::Hazard);
+        }
+
+        self.pickup_item();
         self.dave.verify_input();
         self.dave.move_dave();
 
+        // Dave doesn't know about sounds, so we just watch for the changes in his
+        // state that should make one.
+        if self.dave.is_jumping() && !was_jumping {
+            self.sounds.push(SoundEffect::Jump);
+        }
+
+        if self.dave.bullet().is_some() && !had_bullet {
+            self.sounds.push(SoundEffect::Gunfire);
+        }
+
+        if self.dave.is_jetpacking() {
+            self.sounds.push(SoundEffect::Jetpack);
+        }
+
+        let path = levels[self.misc.level].path();
+        let misc = &self.misc;
         for m in self.monsters.iter_mut() {
-            m.move_monster(self.misc.level, assets);
+            m.move_monster(path);
 
             if self.monster_bullet.is_none() {
-                self.monster_bullet = m.try_fire_bullet(self.dave.pixel_position, self.misc.view_x);
+                self.monster_bullet = m.try_fire_bullet(self.dave.pixel_position, |x| misc.is_visible(x));
             }
 
             m.update();
         }
 
-        self.update_bullets(assets);
+        self.update_bullets();
 
         self.scroll_screen();
-        self.dave.apply_gravity(self.misc.level, assets);
+        self.dave.apply_gravity(&self.tiles);
         self.dave.update();
-        self.update_level();
+        self.update_level(levels);
+
+        if was_alive && !self.dave.is_alive() {
+            self.sounds.push(SoundEffect::DaveDeath);
+        }
     }
 
     pub fn input(&mut self, input: &Input) {
@@ -253,20 +453,35 @@ impl Game {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl MiscParts {
+    // Whether a column of the level is on the screen. With the smooth camera, that's
+    // any column that's even partly on it.
+    fn is_visible(&self, column: i8) -> bool {
+        match self.camera_x {
+            Some(camera_x) => {
+                let left = column as i16 * TILE_SIZE as i16;
+                let width = self.view_width as i16 * TILE_SIZE as i16;
+                left + TILE_SIZE as i16 > camera_x && left < camera_x + width
+            },
+            None => is_visible(column, self.view_x, self.view_width),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Direction {
     Left,
     Middle,
     Right
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum BulletSource {
     Dave,
     Monster,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Bullet {
     pub source: BulletSource,
     pub position: Position<i16>,
@@ -274,7 +489,7 @@ pub struct Bullet {
 }
 
 impl Bullet {
-    fn update_bullet(&mut self, dave_pos: Position<i8>, monsters: &[Monster], level_misc: &MiscParts, assets: &Assets) -> (CollisionType, bool) {
+    fn update_bullet(&mut self, dave_pos: Position<i8>, monsters: &[Monster], level_misc: &MiscParts, tiles: &[TileId]) -> (CollisionType, bool) {
         let dir_mult = match self.direction {
             Direction::Right | Direction::Middle => 1,
             Direction::Left => -1,
@@ -284,8 +499,8 @@ impl Bullet {
 
         let grid_x = (self.position.x / TILE_SIZE as i16) as i8;
         let grid_y = (self.position.y / TILE_SIZE as i16) as i8;
-        let hit = is_clear(level_misc.level, assets, self.position);
-        let visible = is_visible(grid_x, level_misc.view_x);
+        let hit = is_clear(tiles, self.position);
+        let visible = level_misc.is_visible(grid_x);
 
         match (hit, visible) {
             (CollisionType::Wall, _) | (_, false) => (hit, visible),
@@ -329,7 +544,7 @@ pub enum CollisionType {
     Climbable,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
 pub struct Position<T> {
     pub x: T,
     pub y: T,
@@ -346,7 +561,7 @@ impl<T: Add<T, Output=T>> Add<(T, T)> for Position<T> {
     }
 }
 
-pub fn is_clear(level: LevelId, assets: &Assets, pos: Position<i16>) -> CollisionType {
+pub fn is_clear(tiles: &[TileId], pos: Position<i16>) -> CollisionType {
     let grid_x = pos.x as usize / TILE_SIZE as usize;
     let grid_y = pos.y as usize / TILE_SIZE as usize;
 
@@ -354,8 +569,7 @@ pub fn is_clear(level: LevelId, assets: &Assets, pos: Position<i16>) -> Collisio
         return CollisionType::None;
     }
 
-    let level = assets.get_level(level);
-    let tile_type = level.tiles()[grid_y*100+grid_x];
+    let tile_type = tiles[grid_y*100+grid_x];
 
     if tile_type.is_collidable() {
         CollisionType::Wall
@@ -372,10 +586,10 @@ pub fn is_clear(level: LevelId, assets: &Assets, pos: Position<i16>) -> Collisio
     }
 }
 
-pub fn is_visible(pos_x: i8, view_x: i8) -> bool {
+pub fn is_visible(pos_x: i8, view_x: i8, view_width: u8) -> bool {
     if pos_x < view_x {
         false
-    } else if pos_x - view_x < 20 {
+    } else if (pos_x as i16 - view_x as i16) < view_width as i16 {
         true
     } else {
         false
@@ -0,0 +1,491 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    Result, TILE_SIZE,
+    game::Game,
+    dave::HasJetpack,
+    tileset::TileId,
+    renderer::RenderBackend,
+    framebuffer::{SCREEN_WIDTH, screen_width},
+};
+
+// The HUD is a list of elements, drawn in order, so anything drawn over the level
+// (the panel, say) has to come before whatever goes on top of it. The default is the
+// original layout, and others can be loaded from a text file, one element a line:
+//
+//  # element anchor x y [setting value]...
+//  score-label left 1 0
+//  score left 96 1 digits 5 overflow grow
+//  lives right 255 1 icons 3 spacing 16 overflow count
+//  panel left 0 166 height 34
+//
+// Positions are in the game's pixels, on the original 320 wide screen. On a wider
+// screen, anything anchored to the middle or the right moves over with it.
+//
+// Numbers are drawn right to left from their x, with at least `digits` digits (up
+// to 10). Their overflow is what happens to bigger numbers: `wrap` drops the top
+// digits, like the original, `grow` adds more to the left, and `cap` shows all
+// nines.
+// Lives are drawn as up to `icons` icons, `spacing` apart, and past that, `clip`
+// stops at that many, like the original, `grow` draws them all, and `count` draws
+// a single icon with the number next to it.
+//
+// `border` and `panel` stretch across the whole screen, and the fuel bar is drawn
+// inside the fuel border.
+
+const DIGIT_WIDTH: f64 = 8.0;
+// As many as a u32 can have.
+const MAX_DIGITS: u32 = 10;
+const FUEL_SEGMENTS: f64 = 60.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NumberOverflow {
+    Wrap,
+    Grow,
+    Cap,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LivesOverflow {
+    Clip,
+    Grow,
+    Count,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HudItem {
+    ScoreLabel,
+    LevelLabel,
+    LivesLabel,
+    Score { digits: u32, overflow: NumberOverflow },
+    Level { digits: u32, overflow: NumberOverflow },
+    Lives { icons: u32, spacing: f64, overflow: LivesOverflow },
+    Trophy,
+    Gun,
+    Jetpack,
+    Fuel,
+    Border,
+    Panel { height: f64 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HudElement {
+    pub item: HudItem,
+    pub anchor: Anchor,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HudLayout {
+    pub elements: Vec<HudElement>,
+}
+
+// Names for the anchors and overflows, both ways, like the death causes.
+const ANCHOR_NAMES: [(&str, Anchor); 3] = [
+    ("left", Anchor::Left),
+    ("middle", Anchor::Middle),
+    ("right", Anchor::Right),
+];
+
+const NUMBER_OVERFLOW_NAMES: [(&str, NumberOverflow); 3] = [
+    ("wrap", NumberOverflow::Wrap),
+    ("grow", NumberOverflow::Grow),
+    ("cap", NumberOverflow::Cap),
+];
+
+const LIVES_OVERFLOW_NAMES: [(&str, LivesOverflow); 3] = [
+    ("clip", LivesOverflow::Clip),
+    ("grow", LivesOverflow::Grow),
+    ("count", LivesOverflow::Count),
+];
+
+fn name_of<T: PartialEq>(names: &[(&'static str, T)], value: &T) -> &'static str {
+    names.iter()
+        .find(|(_, v)| v == value)
+        .map(|&(name, _)| name)
+        .unwrap()
+}
+
+fn parse_name<T: Copy>(names: &[(&str, T)], s: &str, what: &str) -> Result<T> {
+    Ok(names.iter()
+        .find(|(name, _)| *name == s)
+        .map(|&(_, v)| v)
+        .ok_or_else(|| format!("Invalid {}: {}", what, s))?)
+}
+
+impl Default for HudLayout {
+    fn default() -> HudLayout {
+        use HudItem::*;
+        use Anchor::*;
+
+        // The digits and lives sit a pixel lower than the labels.
+        let top = 1.0;
+
+        let element = |item, anchor, x, y| HudElement { item, anchor, x, y };
+
+        HudLayout {
+            elements: vec![
+                element(ScoreLabel, Left, 1.0, 0.0),
+                element(LevelLabel, Middle, 120.0, 0.0),
+                element(LivesLabel, Right, 200.0, 0.0),
+                element(Score { digits: 5, overflow: NumberOverflow::Grow }, Left, 96.0, top),
+                element(Level { digits: 2, overflow: NumberOverflow::Grow }, Middle, 178.0, top),
+                element(Lives { icons: 3, spacing: 16.0, overflow: LivesOverflow::Count }, Right, 255.0, top),
+                // Drawn over the bottom of the level to fit in the rest.
+                element(Panel { height: 34.0 }, Left, 0.0, 150.0 + TILE_SIZE as f64),
+                element(Trophy, Left, 71.0, 184.0),
+                element(Gun, Right, 255.0, 170.0),
+                element(Jetpack, Left, 1.0, 170.0),
+                element(Fuel, Left, 71.0, 170.0),
+                element(Border, Left, 0.0, 12.0),
+                element(Border, Left, 0.0, 167.0),
+            ],
+        }
+    }
+}
+
+impl HudItem {
+    fn name(&self) -> &'static str {
+        match self {
+            HudItem::ScoreLabel => "score-label",
+            HudItem::LevelLabel => "level-label",
+            HudItem::LivesLabel => "lives-label",
+            HudItem::Score { .. } => "score",
+            HudItem::Level { .. } => "level",
+            HudItem::Lives { .. } => "lives",
+            HudItem::Trophy => "trophy",
+            HudItem::Gun => "gun",
+            HudItem::Jetpack => "jetpack",
+            HudItem::Fuel => "fuel",
+            HudItem::Border => "border",
+            HudItem::Panel { .. } => "panel",
+        }
+    }
+
+    // With the settings left at their defaults.
+    fn from_name(name: &str) -> Result<HudItem> {
+        Ok(match name {
+            "score-label" => HudItem::ScoreLabel,
+            "level-label" => HudItem::LevelLabel,
+            "lives-label" => HudItem::LivesLabel,
+            "score" => HudItem::Score { digits: 5, overflow: NumberOverflow::Grow },
+            "level" => HudItem::Level { digits: 2, overflow: NumberOverflow::Grow },
+            "lives" => HudItem::Lives { icons: 3, spacing: 16.0, overflow: LivesOverflow::Count },
+            "trophy" => HudItem::Trophy,
+            "gun" => HudItem::Gun,
+            "jetpack" => HudItem::Jetpack,
+            "fuel" => HudItem::Fuel,
+            "border" => HudItem::Border,
+            "panel" => HudItem::Panel { height: 34.0 },
+            _ => Err(format!("Invalid HUD element: {}", name))?,
+        })
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match (self, key) {
+            (HudItem::Score { digits, .. }, "digits") | (HudItem::Level { digits, .. }, "digits") => {
+                *digits = value.parse()?;
+                if *digits < 1 || *digits > MAX_DIGITS {
+                    Err(format!("digits has to be from 1 to {}", MAX_DIGITS))?;
+                }
+            },
+            (HudItem::Score { overflow, .. }, "overflow") | (HudItem::Level { overflow, .. }, "overflow") =>
+                *overflow = parse_name(&NUMBER_OVERFLOW_NAMES, value, "overflow")?,
+            (HudItem::Lives { icons, .. }, "icons") => *icons = value.parse()?,
+            (HudItem::Lives { spacing, .. }, "spacing") => *spacing = value.parse()?,
+            (HudItem::Lives { overflow, .. }, "overflow") =>
+                *overflow = parse_name(&LIVES_OVERFLOW_NAMES, value, "overflow")?,
+            (HudItem::Panel { height }, "height") => *height = value.parse()?,
+            (item, _) => Err(format!("{} has no {} setting", item.name(), key))?,
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for HudElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.item.name(), name_of(&ANCHOR_NAMES, &self.anchor), self.x, self.y)?;
+
+        match self.item {
+            HudItem::Score { digits, overflow } | HudItem::Level { digits, overflow } =>
+                write!(f, " digits {} overflow {}", digits, name_of(&NUMBER_OVERFLOW_NAMES, &overflow)),
+            HudItem::Lives { icons, spacing, overflow } =>
+                write!(f, " icons {} spacing {} overflow {}", icons, spacing, name_of(&LIVES_OVERFLOW_NAMES, &overflow)),
+            HudItem::Panel { height } => write!(f, " height {}", height),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FromStr for HudElement {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<HudElement> {
+        let mut parts = s.split_whitespace();
+        let mut next = |what: &str| parts.next().ok_or_else(|| format!("Missing the {}", what));
+
+        let mut item = HudItem::from_name(next("element")?)?;
+        let anchor = parse_name(&ANCHOR_NAMES, next("anchor")?, "anchor")?;
+        let x = next("x position")?.parse()?;
+        let y = next("y position")?.parse()?;
+
+        while let Ok(key) = next("setting") {
+            item.set(key, next("setting value")?)?;
+        }
+
+        Ok(HudElement { item, anchor, x, y })
+    }
+}
+
+// The digits of a number, lowest first, as the overflow allows.
+fn number_digits(value: u32, digits: u32, overflow: NumberOverflow) -> Vec<u32> {
+    let limit = 10u32.checked_pow(digits);
+    let (value, count) = match (overflow, limit) {
+        (NumberOverflow::Wrap, Some(limit)) => (value % limit, digits),
+        (NumberOverflow::Cap, Some(limit)) => (value.min(limit - 1), digits),
+        _ => (value, digits.max(value.checked_ilog10().unwrap_or(0) + 1)),
+    };
+
+    (0..count)
+        .map(|i| 10u32.checked_pow(i).map(|d| (value / d) % 10).unwrap_or(0))
+        .collect()
+}
+
+// How many icons the lives are drawn with, and, when they're counted instead, how
+// far from the first icon the number's last digit goes. The number goes after the
+// icon, so it's drawn from its last digit.
+fn lives_layout(lives: u32, icons: u32, spacing: f64, overflow: LivesOverflow) -> (u32, Option<f64>) {
+    match overflow {
+        LivesOverflow::Clip => (lives.min(icons), None),
+        LivesOverflow::Grow => (lives, None),
+        LivesOverflow::Count if lives > icons => {
+            let digits = lives.ilog10() + 1;
+            (1, Some(spacing + DIGIT_WIDTH * (digits - 1) as f64))
+        },
+        LivesOverflow::Count => (lives, None),
+    }
+}
+
+fn draw_number<B: RenderBackend>(b: &mut B, value: u32, digits: u32, overflow: NumberOverflow, x: f64, y: f64) {
+    for (digit, i) in number_digits(value, digits, overflow).into_iter().zip(0..) {
+        b.draw_tile(TileId::get_digit_tile(digit), x - DIGIT_WIDTH * i as f64, y);
+    }
+}
+
+impl HudLayout {
+    pub fn load<R: BufRead>(input: R) -> Result<HudLayout> {
+        let mut elements = Vec::new();
+
+        for (line_num, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let element = line.parse()
+                .map_err(|e| format!("Invalid HUD element on line {}: {}: {}", line_num + 1, line, e))?;
+            elements.push(element);
+        }
+
+        Ok(HudLayout { elements })
+    }
+
+    pub fn save<W: Write>(&self, mut out: W) -> Result<()> {
+        writeln!(out, "# element anchor x y [setting value]...")?;
+        for element in self.elements.iter() {
+            writeln!(out, "{}", element)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<HudLayout> {
+        HudLayout::load(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        let width = screen_width(game);
+
+        for element in self.elements.iter() {
+            let x = element.x + match element.anchor {
+                Anchor::Left => 0.0,
+                Anchor::Middle => ((width - SCREEN_WIDTH) / 2) as f64,
+                Anchor::Right => (width - SCREEN_WIDTH) as f64,
+            };
+            let y = element.y;
+
+            match element.item {
+                HudItem::ScoreLabel => b.draw_tile(TileId::TILE_UI_SCORE, x, y),
+                HudItem::LevelLabel => b.draw_tile(TileId::TILE_UI_LEVEL, x, y),
+                HudItem::LivesLabel => b.draw_tile(TileId::TILE_UI_DAVES, x, y),
+                HudItem::Score { digits, overflow } => draw_number(b, game.score(), digits, overflow, x, y),
+                HudItem::Level { digits, overflow } => {
+                    let level = game.current_level().val() as u32 + 1;
+                    draw_number(b, level, digits, overflow, x, y);
+                },
+                HudItem::Lives { icons, spacing, overflow } => {
+                    let lives = game.lives() as u32;
+                    let (shown, number_x) = lives_layout(lives, icons, spacing, overflow);
+
+                    for i in 0..shown {
+                        b.draw_tile(TileId::TILE_UI_DAVE, x + spacing * i as f64, y);
+                    }
+
+                    if let Some(number_x) = number_x {
+                        draw_number(b, lives, 1, NumberOverflow::Grow, x + number_x, y);
+                    }
+                },
+                HudItem::Trophy if game.has_trophy() => b.draw_tile(TileId::TILE_UI_TROPHY, x, y),
+                HudItem::Gun if game.has_gun() => b.draw_tile(TileId::TILE_UI_GUN, x, y),
+                HudItem::Jetpack if game.has_jetpack() != HasJetpack::No => b.draw_tile(TileId::TILE_UI_JETPACK, x, y),
+                HudItem::Fuel => {
+                    if let HasJetpack::Yes(fuel) = game.has_jetpack() {
+                        b.draw_tile(TileId::TILE_UI_JETPACK_FUEL_BORDER, x, y);
+
+                        let segments = ((fuel as f64)/255.0 * FUEL_SEGMENTS).ceil() as u32;
+                        for i in 0..segments {
+                            b.draw_tile(TileId::TILE_UI_JETPACK_FUEL_BAR, x + 4.0 + (i*2) as f64, y + 4.0);
+                        }
+                    }
+                },
+                // The border is 32 pixels wide, so there's half of one left over at 30
+                // tiles, say. The last one hangs off the edge.
+                HudItem::Border => {
+                    for i in 0..width.div_ceil(32) {
+                        b.draw_tile(TileId::TILE_UI_BORDER, x + (i * 32) as f64, y);
+                    }
+                },
+                HudItem::Panel { height } => b.fill_rect([0.0, 0.0, 0.0, 1.0], [x, y, width as f64, height]),
+                _ => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_then_load_default() {
+        let layout = HudLayout::default();
+        let mut out = Vec::new();
+        layout.save(&mut out).unwrap();
+
+        let loaded = HudLayout::load(&out[..]).unwrap();
+        assert_eq!(loaded.elements, layout.elements);
+    }
+
+    #[test]
+    fn load_settings() {
+        let layout = HudLayout::load(&b"# a comment\n\n  score right 10 2 digits 7 overflow cap\nlives middle 5 6 overflow grow icons 4\n"[..]).unwrap();
+        assert_eq!(layout.elements, vec![
+            HudElement { item: HudItem::Score { digits: 7, overflow: NumberOverflow::Cap }, anchor: Anchor::Right, x: 10.0, y: 2.0 },
+            HudElement { item: HudItem::Lives { icons: 4, spacing: 16.0, overflow: LivesOverflow::Grow }, anchor: Anchor::Middle, x: 5.0, y: 6.0 },
+        ]);
+    }
+
+    #[test]
+    fn load_rejects_malformed_lines() {
+        let bad = [
+            "dave left 0 0",
+            "score top 0 0",
+            "score left 0",
+            "score left zero 0",
+            "score left 0 0 digits",
+            "score left 0 0 digits 0",
+            "score left 0 0 digits 11",
+            "score left 0 0 digits five",
+            "score left 0 0 overflow clip",
+            "lives left 0 0 overflow wrap",
+            "lives left 0 0 height 3",
+            "trophy left 0 0 icons 3",
+        ];
+
+        for line in bad.iter() {
+            let input = format!("score-label left 1 0\n{}\n", line);
+            let err = HudLayout::load(input.as_bytes()).unwrap_err().to_string();
+            assert!(err.starts_with("Invalid HUD element on line 2"), "{}: {}", line, err);
+        }
+
+        // The most digits a number can have is still fine.
+        assert!(HudLayout::load(&b"score left 0 0 digits 10"[..]).is_ok());
+    }
+
+    #[test]
+    fn number_wrap() {
+        assert_eq!(number_digits(99999, 5, NumberOverflow::Wrap), vec![9, 9, 9, 9, 9]);
+        assert_eq!(number_digits(100000, 5, NumberOverflow::Wrap), vec![0, 0, 0, 0, 0]);
+        assert_eq!(number_digits(123456, 5, NumberOverflow::Wrap), vec![6, 5, 4, 3, 2]);
+        assert_eq!(number_digits(10, 1, NumberOverflow::Wrap), vec![0]);
+        // Nothing is dropped at 10 digits, as a u32 never has more.
+        assert_eq!(number_digits(u32::MAX, 10, NumberOverflow::Wrap), vec![5, 9, 2, 7, 6, 9, 4, 9, 2, 4]);
+    }
+
+    #[test]
+    fn number_cap() {
+        assert_eq!(number_digits(99999, 5, NumberOverflow::Cap), vec![9, 9, 9, 9, 9]);
+        assert_eq!(number_digits(100000, 5, NumberOverflow::Cap), vec![9, 9, 9, 9, 9]);
+        assert_eq!(number_digits(42, 5, NumberOverflow::Cap), vec![2, 4, 0, 0, 0]);
+        assert_eq!(number_digits(u32::MAX, 9, NumberOverflow::Cap), vec![9; 9]);
+        assert_eq!(number_digits(u32::MAX, 10, NumberOverflow::Cap), vec![5, 9, 2, 7, 6, 9, 4, 9, 2, 4]);
+    }
+
+    #[test]
+    fn number_grow() {
+        assert_eq!(number_digits(0, 1, NumberOverflow::Grow), vec![0]);
+        assert_eq!(number_digits(0, 5, NumberOverflow::Grow), vec![0; 5]);
+        assert_eq!(number_digits(99999, 5, NumberOverflow::Grow), vec![9, 9, 9, 9, 9]);
+        assert_eq!(number_digits(100000, 5, NumberOverflow::Grow), vec![0, 0, 0, 0, 0, 1]);
+        assert_eq!(number_digits(999_999_999, 2, NumberOverflow::Grow), vec![9; 9]);
+        assert_eq!(number_digits(1_000_000_000, 2, NumberOverflow::Grow), vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(number_digits(u32::MAX, 1, NumberOverflow::Grow), vec![5, 9, 2, 7, 6, 9, 4, 9, 2, 4]);
+        assert_eq!(number_digits(7, 10, NumberOverflow::Grow), vec![7, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn lives_clip_and_grow() {
+        assert_eq!(lives_layout(0, 3, 16.0, LivesOverflow::Clip), (0, None));
+        assert_eq!(lives_layout(3, 3, 16.0, LivesOverflow::Clip), (3, None));
+        assert_eq!(lives_layout(4, 3, 16.0, LivesOverflow::Clip), (3, None));
+        assert_eq!(lives_layout(4, 3, 16.0, LivesOverflow::Grow), (4, None));
+        assert_eq!(lives_layout(255, 3, 16.0, LivesOverflow::Grow), (255, None));
+    }
+
+    #[test]
+    fn lives_count() {
+        assert_eq!(lives_layout(0, 3, 16.0, LivesOverflow::Count), (0, None));
+        assert_eq!(lives_layout(3, 3, 16.0, LivesOverflow::Count), (3, None));
+        // Past the icons, the number starts where the second icon would be, and its
+        // last digit moves over as it gets longer.
+        assert_eq!(lives_layout(4, 3, 16.0, LivesOverflow::Count), (1, Some(16.0)));
+        assert_eq!(lives_layout(9, 3, 16.0, LivesOverflow::Count), (1, Some(16.0)));
+        assert_eq!(lives_layout(10, 3, 16.0, LivesOverflow::Count), (1, Some(24.0)));
+        assert_eq!(lives_layout(99, 3, 16.0, LivesOverflow::Count), (1, Some(24.0)));
+        assert_eq!(lives_layout(100, 3, 16.0, LivesOverflow::Count), (1, Some(32.0)));
+        assert_eq!(lives_layout(255, 3, 16.0, LivesOverflow::Count), (1, Some(32.0)));
+        // With no icons, even one life is counted.
+        assert_eq!(lives_layout(1, 0, 10.0, LivesOverflow::Count), (1, Some(10.0)));
+    }
+}
\ No newline at end of file
@@ -1,8 +1,16 @@
+use std::{
+    fmt,
+    ops::BitOr,
+    str::FromStr,
+};
+
 use piston_window::{
     ButtonArgs, ButtonState, Button,
     keyboard::Key,
 };
 
+use crate::bindings::{Action, KeyBindings};
+
 // As far as I can tell, using Piston's ButtonArgs directly only makes
 // the input happen once every button press. The result of that is the
 // event happens once when you press it, and then repeatedly as the
@@ -13,7 +21,7 @@ use piston_window::{
 // us to this input "buffer" which causes the input to be constant from
 // first press to release.
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Input {
     right: bool,
     left: bool,
@@ -21,23 +29,69 @@ pub struct Input {
     toggle_jetpack: bool,
     fire: bool,
     jump: bool,
+    // Not a game button, so it's never recorded. Holding it winds the game back.
+    rewind: bool,
+    // An action can have more than one key, so it's only let go when all of them are.
+    held_keys: Vec<Key>,
 }
 
 impl Input {
-    pub fn update(&mut self, button: ButtonArgs) {
+    pub fn update(&mut self, button: ButtonArgs, bindings: &KeyBindings) {
         if let ButtonArgs{state, button: Button::Keyboard(key), ..} = button {
-            match key {
-                Key::Right => self.right = state == ButtonState::Press,
-                Key::Left => self.left = state == ButtonState::Press,
-                Key::Up => self.jump = state == ButtonState::Press,
-                Key::Down => self.down = state == ButtonState::Press,
-                Key::LCtrl => self.fire = state == ButtonState::Press,
-                Key::LAlt => self.toggle_jetpack = state == ButtonState::Press,
-                _ => {}
+            let action = match bindings.action(key) {
+                Some(action) => action,
+                None => return,
+            };
+
+            self.held_keys.retain(|&k| k != key);
+            if state == ButtonState::Press {
+                self.held_keys.push(key);
+            }
+            let held = self.held_keys.iter().any(|&k| bindings.action(k) == Some(action));
+
+            match action {
+                Action::Right => self.right = held,
+                Action::Left => self.left = held,
+                Action::Jump => self.jump = held,
+                Action::Down => self.down = held,
+                Action::Fire => self.fire = held,
+                // The jetpack goes on and off with each press, so only the key that
+                // was pressed counts.
+                Action::Jetpack => self.toggle_jetpack = state == ButtonState::Press,
+                Action::Rewind => self.rewind = held,
+                // The TAS's keys are handled by the TAS itself.
+                Action::Pause | Action::Step | Action::TasMode | Action::Export | Action::Import
+                    | Action::SaveSlot | Action::LoadSlot => {},
             }
         }
     }
 
+    pub fn from_buttons(buttons: Buttons) -> Input {
+        Input {
+            right: buttons.contains(Buttons::RIGHT),
+            left: buttons.contains(Buttons::LEFT),
+            down: buttons.contains(Buttons::DOWN),
+            toggle_jetpack: buttons.contains(Buttons::JETPACK),
+            fire: buttons.contains(Buttons::FIRE),
+            jump: buttons.contains(Buttons::JUMP),
+            rewind: false,
+            held_keys: Vec::new(),
+        }
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        [
+            (self.right, Buttons::RIGHT),
+            (self.left, Buttons::LEFT),
+            (self.jump, Buttons::JUMP),
+            (self.down, Buttons::DOWN),
+            (self.fire, Buttons::FIRE),
+            (self.toggle_jetpack, Buttons::JETPACK),
+        ].iter()
+            .filter(|(held, _)| *held)
+            .fold(Buttons::NONE, |acc, &(_, b)| acc | b)
+    }
+
     pub fn clear_toggles(&mut self) {
         self.toggle_jetpack = false;
     }
@@ -65,4 +119,94 @@ impl Input {
     pub fn fire(&self) -> bool {
         self.fire
     }
+
+    pub fn rewind(&self) -> bool {
+        self.rewind
+    }
+}
+
+// The state of every button for a single update, packed into a bit set. This is
+// what gets recorded and fed back in when the game isn't being driven by the
+// keyboard.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Buttons(u8);
+
+impl Buttons {
+    pub const NONE: Buttons = Buttons(0);
+    pub const RIGHT: Buttons = Buttons(1 << 0);
+    pub const LEFT: Buttons = Buttons(1 << 1);
+    pub const JUMP: Buttons = Buttons(1 << 2);
+    pub const DOWN: Buttons = Buttons(1 << 3);
+    pub const FIRE: Buttons = Buttons(1 << 4);
+    pub const JETPACK: Buttons = Buttons(1 << 5);
+
+    const ALL_BITS: u8 = 0x3F;
+
+    pub fn from_bits(bits: u8) -> Option<Buttons> {
+        if bits & !Buttons::ALL_BITS == 0 {
+            Some(Buttons(bits))
+        } else {
+            None
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: Buttons) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Buttons {
+    type Output = Buttons;
+
+    fn bitor(self, other: Buttons) -> Buttons {
+        Buttons(self.0 | other.0)
+    }
+}
+
+// Written as one letter per held button, so input logs can be edited by hand:
+// R(ight), L(eft), U(p, to jump), D(own), F(ire) and J(etpack). No buttons is `.`.
+const BUTTON_CHARS: [(char, Buttons); 6] = [
+    ('R', Buttons::RIGHT),
+    ('L', Buttons::LEFT),
+    ('U', Buttons::JUMP),
+    ('D', Buttons::DOWN),
+    ('F', Buttons::FIRE),
+    ('J', Buttons::JETPACK),
+];
+
+impl fmt::Display for Buttons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Buttons::NONE {
+            return write!(f, ".");
+        }
+
+        for &(c, b) in BUTTON_CHARS.iter() {
+            if self.contains(b) {
+                write!(f, "{}", c)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Buttons {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Buttons, String> {
+        if s == "." {
+            return Ok(Buttons::NONE);
+        }
+
+        s.chars().try_fold(Buttons::NONE, |acc, c| {
+            BUTTON_CHARS.iter()
+                .find(|&&(bc, _)| bc == c.to_ascii_uppercase())
+                .map(|&(_, b)| acc | b)
+                .ok_or_else(|| format!("Invalid button: {}", c))
+        })
+    }
 }
\ No newline at end of file
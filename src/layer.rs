@@ -0,0 +1,96 @@
+use image::{RgbaImage, GenericImage};
+
+use crate::{
+    TILE_SIZE,
+    tileset::*,
+};
+
+// The tiles of a level that look the same every frame, drawn together into one
+// image, so the window can draw most of the level with a single texture rather
+// than a tile at a time. The animated tiles (fire, water, weeds and the trophies)
+// are left out, and drawn over the top as usual.
+//
+// It's kept in step with the game's own copy of the level. The game stamps its
+// tiles whenever they change (see Game::tiles_stamp), so most frames all it takes
+// is comparing stamps. When they do change, only the tiles that are different are
+// redrawn, which is usually just a pickup that's been taken, unless it's a whole
+// new level.
+//
+// With a tile pack (see TilePack), it's drawn at the pack's scale, so the window
+// gets the replacements.
+
+pub const LAYER_WIDTH: u32 = 100 * TILE_SIZE;
+pub const LAYER_HEIGHT: u32 = 10 * TILE_SIZE;
+
+pub struct StaticLayer {
+    // The stamp of the tiles it was last drawn from.
+    stamp: Option<u64>,
+    tiles: Vec<TileId>,
+    image: RgbaImage,
+    scale: u32,
+}
+
+impl Default for StaticLayer {
+    fn default() -> StaticLayer {
+        StaticLayer::new(1)
+    }
+}
+
+impl StaticLayer {
+    pub fn new(scale: u32) -> StaticLayer {
+        StaticLayer {
+            stamp: None,
+            tiles: Vec::new(),
+            image: RgbaImage::new(LAYER_WIDTH * scale, LAYER_HEIGHT * scale),
+            scale,
+        }
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn stamp(&self) -> Option<u64> {
+        self.stamp
+    }
+
+    // Returns whether anything had to be redrawn.
+    // The pack has to be at the layer's scale.
+    pub fn update(&mut self, stamp: u64, tiles: &[TileId], images: &TileImages, pack: &TilePack) -> bool {
+        if self.stamp == Some(stamp) {
+            return false;
+        }
+        self.stamp = Some(stamp);
+
+        if self.tiles == tiles {
+            return false;
+        }
+
+        let redraw_all = self.tiles.len() != tiles.len();
+        for (i, &tile) in tiles.iter().enumerate() {
+            if redraw_all || self.tiles[i] != tile {
+                self.draw_tile(i, tile, images, pack);
+            }
+        }
+
+        self.tiles = tiles.to_vec();
+        true
+    }
+
+    fn draw_tile(&mut self, index: usize, tile: TileId, images: &TileImages, pack: &TilePack) {
+        let cell = TILE_SIZE * self.scale;
+        let x = (index % 100) as u32 * cell;
+        let y = (index / 100) as u32 * cell;
+
+        if tile.is_animated() {
+            let blank = RgbaImage::new(cell, cell);
+            self.image.copy_from(&blank, x, y);
+        } else {
+            self.image.copy_from(&*pack.scaled_image(tile, images), x, y);
+        }
+    }
+}
\ No newline at end of file
@@ -13,10 +13,9 @@ use crate::{
     game::Position,
     monster::Monster,
 };
-use std::ops::IndexMut;
 
 const NUM_LEVELS: usize = 10;
-const MONSTER_PATH_LEN: usize = 128;
+pub const MONSTER_PATH_LEN: usize = 128;
 
 // Because the level list will be accessed on every frame, I've opted to bypass the
 // bounds check. To ensure that the access is still safe, i've wrapped the vector of
@@ -40,16 +39,18 @@ impl Index<LevelId> for Levels {
     }
 }
 
-impl IndexMut<LevelId> for Levels {
-    fn index_mut(&mut self, idx:LevelId) -> &mut Self::Output {
-        unsafe { self.0.get_unchecked_mut(idx.0) }
-    }
-}
-
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct LevelId(usize);
 
 impl LevelId {
+    pub fn new(id: usize) -> Result<LevelId> {
+        if id < NUM_LEVELS {
+            Ok(LevelId(id))
+        } else {
+            Err(format!("Invalid level id: {}", id))?
+        }
+    }
+
     pub fn next(self) -> Option<LevelId> {
         if self.0 < (NUM_LEVELS - 1) {
             Some(LevelId(self.0 + 1))
@@ -158,10 +159,6 @@ impl Level {
     pub fn tiles(&self) -> &[TileId] {
         &self.tiles
     }
-
-    pub fn tiles_mut(&mut self) -> &mut [TileId] {
-        &mut self.tiles
-    }
 }
 
 pub struct MonsterPath([Position<i16>; 128]);
@@ -182,7 +179,7 @@ impl Index<MonsterPathIndex> for MonsterPath {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct MonsterPathIndex(usize);
 
 impl MonsterPathIndex {
@@ -0,0 +1,38 @@
+use std::error::Error;
+
+pub mod tileset;
+pub mod layer;
+pub mod level;
+pub mod renderer;
+pub mod framebuffer;
+pub mod postprocess;
+pub mod display;
+pub mod camera;
+pub mod text;
+pub mod hud;
+pub mod accessibility;
+pub mod debug;
+pub mod game;
+pub mod assets;
+pub mod input;
+pub mod bindings;
+pub mod rebind;
+pub mod dave;
+pub mod monster;
+pub mod audio;
+pub mod env;
+pub mod batch;
+pub mod replay;
+pub mod solver;
+pub mod tas;
+pub mod rewind;
+pub mod death_replay;
+pub mod deaths;
+pub mod capture;
+pub mod terminal;
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+pub const SCALE: u32 = 3;
+pub const TILE_SIZE: u32 = 16;
+pub const UPDATES_PER_SECOND: u64 = 30;
\ No newline at end of file
@@ -1,6 +1,4 @@
-use std::{
-    error::Error,
-};
+use std::{env, path::PathBuf};
 
 use piston::{
     window::WindowSettings,
@@ -8,66 +6,322 @@ use piston::{
     input::*,
 };
 use piston_window::{
-    PistonWindow as Window, OpenGL
+    PistonWindow as Window, OpenGL, AdvancedWindow, Window as _,
 };
 
-mod tileset;
-mod level;
-mod renderer;
-mod game;
-mod assets;
-mod input;
-mod dave;
-mod monster;
-
-use crate::{
+use ddave::{
+    Result, SCALE, TILE_SIZE, UPDATES_PER_SECOND,
     game::*,
     assets::*,
     input::Input,
+    bindings::KeyBindings,
+    rebind::RebindMenu,
     renderer::*,
+    display::DisplaySettings,
+    camera::*,
+    hud::HudLayout,
+    debug::DebugOverlay,
+    postprocess::PostProcess,
+    accessibility::Palette,
+    tileset::{TilePack, load_tile_images, pack_atlas},
+    audio::*,
+    tas::Tas,
+    rewind::Rewind,
+    death_replay::DeathReplay,
+    deaths::*,
+    capture::*,
 };
 
-type Result<T> = std::result::Result<T, Box<dyn Error>>;
-
 const GL_VERSION: OpenGL = OpenGL::V4_5;
-const SCALE: u32 = 3;
-const TILE_SIZE: u32 = 16;
+
+const WIDESCREEN_VIEW_WIDTH: u8 = 30;
+
+fn set_fullscreen(window: &Window, fullscreen: bool) {
+    let glutin_window = window.window.ctx.window();
+    let monitor = if fullscreen {
+        Some(glutin_window.get_current_monitor())
+    } else {
+        None
+    };
+
+    glutin_window.set_fullscreen(monitor);
+}
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+
+    // The sounds sfx_ext read out of dave.exe. Which of them goes with which effect
+    // hasn't been checked against the original, so they're only used when asked for,
+    // and the built-in sounds are used otherwise.
+    let sound_file = match args.iter().position(|a| a == "--sounds") {
+        Some(i) => Some(PathBuf::from(args.get(i + 1).ok_or("--sounds needs a file written by sfx_ext")?)),
+        None => None,
+    };
+
+    // Renders the sound effects without opening the game window or needing a sound device.
+    if let Some(i) = args.iter().position(|a| a == "--dump-sfx") {
+        let dir = args.get(i + 1).map(String::as_str).unwrap_or(".");
+        SoundBank::load(SAMPLE_RATE, sound_file.as_deref())?.render_wavs(dir)?;
+        return Ok(());
+    }
+
+    // A folder of larger tiles to draw instead of the originals. See TilePack.
+    let tile_pack = match args.iter().position(|a| a == "--tile-pack") {
+        Some(i) => Some(args.get(i + 1).ok_or("--tile-pack needs a folder")?.as_str()),
+        None => None,
+    };
+
+    // CRT effects and smoother upscaling, done on the CPU. See postprocess.rs.
+    let post_process = match args.iter().position(|a| a == "--filter") {
+        Some(i) => args.get(i + 1).ok_or("--filter needs a list of filters")?.parse()?,
+        None => PostProcess::default(),
+    };
+
+    // The filters draw the frame on the CPU, from the original tiles, so they'd
+    // quietly drop the pack.
+    if tile_pack.is_some() && post_process.is_enabled() {
+        Err("--tile-pack can't be used with --filter, as the filters only draw the original tiles")?;
+    }
+
+    // Writes out every tile, packed together as they are for drawing, to check over.
+    if let Some(i) = args.iter().position(|a| a == "--dump-atlas") {
+        let path = args.get(i + 1).map(String::as_str).unwrap_or("atlas.png");
+        let tiles = load_tile_images()?;
+        let pack = match tile_pack {
+            Some(dir) => TilePack::load(dir, &tiles)?,
+            None => TilePack::default(),
+        };
+        pack_atlas(&tiles, &pack).image().save(path)?;
+        println!("Saved the tile atlas, with {} tiles from the tile pack, to {}", pack.replaced(), path);
+        return Ok(());
+    }
+
+    // Writes out the default HUD layout, as a starting point for a new one.
+    if let Some(i) = args.iter().position(|a| a == "--dump-hud") {
+        let path = args.get(i + 1).map(String::as_str).unwrap_or("hud.txt");
+        HudLayout::default().save_file(path)?;
+        println!("Saved the HUD layout to {}", path);
+        return Ok(());
+    }
+
+    let display = DisplaySettings {
+        integer_scaling: args.iter().any(|a| a == "--integer-scaling"),
+        aspect_correction: args.iter().any(|a| a == "--aspect-correction"),
+    };
+
+    // The window can be resized afterwards, and the game is scaled to fit.
+    let scale = match args.iter().position(|a| a == "--scale") {
+        Some(i) => args.get(i + 1).ok_or("--scale needs a scale")?.parse()?,
+        None => SCALE,
+    };
+
+    let mut fullscreen = args.iter().any(|a| a == "--fullscreen");
+
+    // How many tiles of the level are shown at once. 30 fits a 16:9 screen.
+    let view_width = match args.iter().position(|a| a == "--view-width") {
+        Some(i) => args.get(i + 1).ok_or("--view-width needs a number of tiles")?.parse()?,
+        None if args.iter().any(|a| a == "--widescreen") => WIDESCREEN_VIEW_WIDTH,
+        None => CLASSIC_VIEW_WIDTH,
+    };
+
     let mut window: Window = WindowSettings::new(
             "Dangerous Dave",
-            [320 * SCALE, 200 * SCALE]
+            display.window_size(view_width as u32 * TILE_SIZE, scale)
         )
         .graphics_api(GL_VERSION)
         .exit_on_esc(false)
+        .fullscreen(fullscreen)
         .build()?;
 
     // Making sure to limit the frame time and update rate.
     window.set_max_fps(30);
-    window.set_ups(30);
+    window.set_ups(UPDATES_PER_SECOND);
+
+    // Colours for the common colour vision deficiencies. See accessibility.rs.
+    let palette = match args.iter().position(|a| a == "--palette") {
+        Some(i) => args.get(i + 1).ok_or("--palette needs protanopia, deuteranopia or tritanopia")?.parse()?,
+        None => Palette::Original,
+    };
 
-    let mut assets = Assets::init(window.create_texture_context())?;
+    let assets = Assets::init(window.create_texture_context(), tile_pack, palette)?;
     let mut input = Input::default();
-    let mut game = Game::init()?;
-    let mut renderer = Renderer::new();
+
+    // The user's own keys, if they've changed them, or the keys in a file of their
+    // choosing. Changes made in the game are saved back to the same place.
+    let keys_path = match args.iter().position(|a| a == "--keys") {
+        Some(i) => Some(PathBuf::from(args.get(i + 1).ok_or("--keys needs a key bindings file")?)),
+        None => KeyBindings::user_file(),
+    };
+    let bindings = match &keys_path {
+        Some(path) => KeyBindings::load_or_default(path)?,
+        None => KeyBindings::default(),
+    };
+    let mut rebind = RebindMenu::new(bindings, keys_path);
+
+    let mut game = Game::init(assets.levels())?.with_view_width(view_width);
+    // The classic camera scrolls a tile at a time, like the original.
+    let camera = if args.iter().any(|a| a == "--smooth-camera") {
+        let mut smooth = SmoothCamera::default();
+        if let Some(i) = args.iter().position(|a| a == "--camera-dead-zone") {
+            smooth.dead_zone = args.get(i + 1).ok_or("--camera-dead-zone needs a number of pixels")?.parse()?;
+        }
+        if let Some(i) = args.iter().position(|a| a == "--camera-easing") {
+            smooth.easing = args.get(i + 1).ok_or("--camera-easing needs a number from 0 to 1")?.parse()?;
+        }
+        CameraMode::Smooth(smooth)
+    } else {
+        CameraMode::Classic
+    };
+
+    let hud = match args.iter().position(|a| a == "--hud") {
+        Some(i) => HudLayout::load_file(args.get(i + 1).ok_or("--hud needs a layout file")?)?,
+        None => HudLayout::default(),
+    };
+
+    let mut renderer = Renderer::new()
+        .display(display)
+        .camera(camera)
+        .hud(hud)
+        .post_process(post_process)
+        .high_contrast(args.iter().any(|a| a == "--high-contrast"));
+    let mut audio = Audio::init(sound_file.as_deref())?;
+    let mut rewind = Rewind::default();
+    let mut death_replay = DeathReplay::default();
+    let mut debug = DebugOverlay::default();
+    let mut cursor = [0.0, 0.0];
+
+    // Screenshots and recordings are at the game's own resolution, unless scaled up.
+    let capture_scale = match args.iter().position(|a| a == "--capture-scale") {
+        Some(i) => args.get(i + 1).ok_or("--capture-scale needs a scale")?.parse()?,
+        None => 1,
+    };
+
+    // These are drawn on the CPU too, so they can't use the pack either.
+    if tile_pack.is_some() {
+        println!("Screenshots and recordings are drawn with the original tiles, not the tile pack");
+    }
+
+    let mut capture = Capture::new(capture_scale);
+    if let Some(i) = args.iter().position(|a| a == "--record") {
+        let path = args.get(i + 1).ok_or("--record needs a GIF or folder path")?;
+        capture.record(path)?;
+    }
+
+    // Tool-assisted mode. See tas.rs for the controls.
+    let mut tas = match args.iter().position(|a| a == "--tas") {
+        Some(i) => {
+            let log_path = args.get(i + 1).map(String::as_str).unwrap_or("tas.txt");
+            let tas = Tas::init(log_path, &mut game, assets.levels())?;
+            window.set_title(tas.title());
+            Some(tas)
+        },
+        None => None,
+    };
 
     while let Some(e) = window.next() {
-        if let Some(key) = e.button_args() {
-            input.update(key);
+        if let Some(position) = e.mouse_cursor_args() {
+            cursor = position;
+        }
+
+        // The key bindings screen takes every button while it's open, and the game
+        // waits. Whatever was held is let go, as the releases won't get through.
+        let button = e.button_args().filter(|&key| !rebind.handle_button(key));
+        if rebind.is_open() {
+            input = Input::default();
         }
 
-        if let Some(_) = e.update_args() {
-            game.input(&input);
-            game.update(&mut assets);
-            if game.quit() {
-                break;
+        if let Some(key) = button {
+            input.update(key, rebind.bindings());
+            debug.handle_button(key);
+
+            if debug.is_click(key) {
+                let size = window.size();
+                debug.select(renderer.level_position(&game, [size.width, size.height], cursor));
+            }
+
+            if let ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(Key::F11), .. } = key {
+                fullscreen = !fullscreen;
+                set_fullscreen(&window, fullscreen);
+            }
+            if death_replay.handle_button(key) {
+                window.set_title(death_replay.title());
             }
 
-            input.clear_toggles();
-            renderer.update();
+            capture.handle_button(key, || render_frame(&renderer, &game, assets.tile_images()))?;
+
+            if let Some(tas) = &mut tas {
+                tas.handle_button(key, rebind.bindings(), &mut game, assets.levels())?;
+                window.set_title(tas.title());
+            }
         }
 
-        renderer.render(&mut window, &e, &game, &assets);
+        if e.update_args().is_some() && !rebind.is_open() {
+            let update_input = match &mut tas {
+                Some(tas) => tas.next_input(input.buttons()).map(Input::from_buttons),
+                // The replay takes the place of updates until it's finished.
+                None if death_replay.is_playing() => {
+                    death_replay.update(assets.levels());
+                    window.set_title(death_replay.title());
+                    None
+                },
+                // The TAS has its own save states, so rewinding is only for normal play.
+                None if input.rewind() => {
+                    if rewind.step_back(&mut game, renderer.camera_state_mut(), assets.levels()) {
+                        death_replay.step_back();
+                    }
+                    None
+                },
+                None => Some(input.clone()),
+            };
+
+            // The TAS holds off updates while it's paused.
+            if let Some(update_input) = update_input {
+                // Neither is used in the TAS, which has its own save states.
+                if tas.is_none() {
+                    rewind.record(&game, renderer.camera_state(), assets.levels());
+                    death_replay.record(&game, update_input.buttons(), assets.levels());
+                }
+                game.set_camera_x(renderer.camera_state().smooth_view_x());
+                game.input(&update_input);
+                game.update(assets.levels());
+                if game.quit() {
+                    break;
+                }
+
+                // Deaths in the TAS are mostly deliberate, so they're left out of the log.
+                if let (Some(death), None) = (game.take_death(), &tas) {
+                    if let Err(e) = append_death(DEATH_LOG_FILE, &death) {
+                        println!("Couldn't log death: {}", e);
+                    }
+                }
+
+                if tas.is_none() {
+                    death_replay.check(&game, assets.levels());
+                }
+
+                audio.update();
+                for sound in game.drain_sounds() {
+                    audio.play(sound);
+                }
+
+                input.clear_toggles();
+                renderer.update(&game);
+
+                capture.update(|| render_frame(&renderer, &game, assets.tile_images()))?;
+
+                if let Some(tas) = &tas {
+                    window.set_title(tas.title());
+                }
+            }
+        }
+
+        match death_replay.game() {
+            Some(replay_game) => renderer.render_death_replay(&mut window, &e, replay_game, &assets, &rebind)?,
+            None => {
+                let overlays = Overlays { debug: &debug, tas: tas.as_ref(), rebind: &rebind };
+                renderer.render(&mut window, &e, &game, &assets, &overlays)?;
+            },
+        }
     }
 
     Ok(())
@@ -2,11 +2,10 @@ use crate::{
     TILE_SIZE,
     tileset::*,
     game::*,
-    assets::*,
     level::*,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum MonsterState {
     Live {
         position: Position<i8>,
@@ -23,7 +22,7 @@ pub enum MonsterState {
     Dead
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Monster {
     state: MonsterState,
 }
@@ -66,6 +65,33 @@ impl Monster {
             _ => true,
         }
     }
+
+    // The pixel positions the monster will pass through from here to the end of its
+    // path, where it goes back to the start.
+    pub fn remaining_path(&self, path: &MonsterPath) -> Vec<Position<i16>> {
+        let mut points = Vec::new();
+
+        if let MonsterState::Live {pixel_position, next_px, path_index, ..} = self.state {
+            let mut pos = pixel_position + (next_px.x, next_px.y);
+            points.push(pixel_position);
+            points.push(pos);
+
+            // Paths that never end go round forever, so they're cut off at a lap.
+            let mut index = path_index;
+            for _ in 0..MONSTER_PATH_LEN {
+                let step = path[index];
+                if step == MonsterPath::PATH_END {
+                    break;
+                }
+
+                pos = pos + (step.x, step.y);
+                points.push(pos);
+                index = index.next();
+            }
+        }
+
+        points
+    }
 }
 
 impl Monster {
@@ -90,11 +116,9 @@ impl Monster {
         }
     }
 
-    pub fn move_monster(&mut self, level: LevelId, assets: &Assets) {
+    pub fn move_monster(&mut self, path: &MonsterPath) {
         match &mut self.state {
             MonsterState::Live {position, pixel_position, next_px, path_index, ..} => {
-                let path = assets.get_level(level).path();
-
                 for _ in 0..2 {
                     if next_px.x == 0 && next_px.y == 0 {
                         *next_px = path[*path_index];
@@ -132,9 +156,11 @@ impl Monster {
         }
     }
 
-    pub fn try_fire_bullet(&mut self, dave_pos: Position<i16>, view_x: i8) -> Option<Bullet> {
+    // Monsters only shoot while they're on the screen, which `is_visible` says by
+    // the column.
+    pub fn try_fire_bullet<F: Fn(i8) -> bool>(&mut self, dave_pos: Position<i16>, is_visible: F) -> Option<Bullet> {
         match &mut self.state {
-            MonsterState::Live {position, pixel_position, ..} if is_visible(position.x, view_x) => {
+            MonsterState::Live {position, pixel_position, ..} if is_visible(position.x) => {
                 let dir = if dave_pos.x < pixel_position.x {
                     Direction::Left
                 } else {
@@ -0,0 +1,325 @@
+use std::str::FromStr;
+
+use image::{Rgba, RgbaImage};
+
+use crate::Result;
+
+// Filters applied to a finished frame on the CPU, so they work on any graphics card,
+// and in the offscreen tools too. The frame is doubled in size first, which gives
+// the scanlines and the phosphor mask room to show, and the window scales that up
+// the rest of the way.
+//
+// They're picked with a comma separated list, like `scale2x,scanlines=0.5,bloom`:
+//
+//  nearest           Doubles each pixel (the default).
+//  scale2x           Doubles each pixel, rounding off diagonal edges (EPX).
+//  hq2x              Doubles each pixel, blending along edges between different colours.
+//  scanlines[=0-1]   Darkens every other line, by 0.5 unless given.
+//  mask[=0-1]        Tints columns red, green and blue in turn, like an aperture
+//                    grille, by 0.3 unless given.
+//  bloom[=0-1]       Bright areas glow into their surroundings, by 0.3 unless given.
+//  crt               scale2x, scanlines, mask and bloom together.
+
+pub const POST_PROCESS_SCALE: u32 = 2;
+
+const DEFAULT_SCANLINES: f32 = 0.5;
+const DEFAULT_MASK: f32 = 0.3;
+const DEFAULT_BLOOM: f32 = 0.3;
+
+// How far the bloom spreads, in pixels of the doubled frame.
+const BLOOM_RADIUS: i32 = 3;
+// How bright a pixel has to be to glow, from 0 to 1. Above it, the glow fades in,
+// so that the brightest colours glow the most.
+const BLOOM_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Upscaler {
+    Nearest,
+    Scale2x,
+    Hq2x,
+}
+
+// Each of the effects goes from 0 (off) to 1 (as strong as it goes).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PostProcess {
+    pub upscaler: Upscaler,
+    pub scanlines: f32,
+    pub mask: f32,
+    pub bloom: f32,
+}
+
+impl Default for PostProcess {
+    fn default() -> PostProcess {
+        PostProcess {
+            upscaler: Upscaler::Nearest,
+            scanlines: 0.0,
+            mask: 0.0,
+            bloom: 0.0,
+        }
+    }
+}
+
+impl PostProcess {
+    pub fn crt() -> PostProcess {
+        PostProcess {
+            upscaler: Upscaler::Scale2x,
+            scanlines: DEFAULT_SCANLINES,
+            mask: DEFAULT_MASK,
+            bloom: DEFAULT_BLOOM,
+        }
+    }
+
+    // With nothing to do, the window draws the game directly, as it always has.
+    pub fn is_enabled(&self) -> bool {
+        *self != PostProcess::default()
+    }
+
+    // Returns the frame at twice its size, with the filters applied.
+    pub fn apply(&self, frame: &RgbaImage) -> RgbaImage {
+        let mut image = match self.upscaler {
+            Upscaler::Nearest => nearest2x(frame),
+            Upscaler::Scale2x => scale2x(frame),
+            Upscaler::Hq2x => hq2x(frame),
+        };
+
+        // The glow comes from the picture before the scanlines and mask darken it,
+        // so it fills in the gaps between them, like it does on a real screen.
+        let glow = if self.bloom > 0.0 { Some(blur(&bright_pass(&image, BLOOM_THRESHOLD), BLOOM_RADIUS)) } else { None };
+
+        if self.scanlines > 0.0 {
+            let keep = 1.0 - self.scanlines.min(1.0);
+            for (_, y, p) in image.enumerate_pixels_mut() {
+                if y % 2 == 1 {
+                    scale_channels(p, [keep; 3]);
+                }
+            }
+        }
+
+        if self.mask > 0.0 {
+            let keep = 1.0 - self.mask.min(1.0);
+            for (x, _, p) in image.enumerate_pixels_mut() {
+                let mut factors = [keep; 3];
+                factors[x as usize % 3] = 1.0;
+                scale_channels(p, factors);
+            }
+        }
+
+        if let Some(glow) = glow {
+            for (p, g) in image.pixels_mut().zip(glow.pixels()) {
+                for i in 0..3 {
+                    p[i] = (p[i] as f32 + g[i] as f32 * self.bloom).min(255.0) as u8;
+                }
+            }
+        }
+
+        image
+    }
+}
+
+fn scale_channels(p: &mut Rgba<u8>, factors: [f32; 3]) {
+    for i in 0..3 {
+        p[i] = (p[i] as f32 * factors[i]) as u8;
+    }
+}
+
+fn parse_strength(value: Option<&str>, default: f32) -> Result<f32> {
+    match value {
+        Some(v) => match v.parse::<f32>() {
+            Ok(s) if (0.0..=1.0).contains(&s) => Ok(s),
+            _ => Err(format!("Filter strength must be from 0 to 1, not {}", v))?,
+        },
+        None => Ok(default),
+    }
+}
+
+impl FromStr for PostProcess {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<PostProcess> {
+        let mut post = PostProcess::default();
+
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let mut parts = item.splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            let value = parts.next();
+
+            match (name, value) {
+                ("nearest", None) => post.upscaler = Upscaler::Nearest,
+                ("scale2x", None) => post.upscaler = Upscaler::Scale2x,
+                ("hq2x", None) => post.upscaler = Upscaler::Hq2x,
+                ("crt", None) => post = PostProcess::crt(),
+                ("scanlines", v) => post.scanlines = parse_strength(v, DEFAULT_SCANLINES)?,
+                ("mask", v) => post.mask = parse_strength(v, DEFAULT_MASK)?,
+                ("bloom", v) => post.bloom = parse_strength(v, DEFAULT_BLOOM)?,
+                _ => Err(format!("Unknown filter: {}", item))?,
+            }
+        }
+
+        Ok(post)
+    }
+}
+
+// The pixel at (x, y), or the nearest one on the edge if that's outside the frame.
+fn pixel_at(frame: &RgbaImage, x: i32, y: i32) -> Rgba<u8> {
+    let x = x.clamp(0, frame.width() as i32 - 1);
+    let y = y.clamp(0, frame.height() as i32 - 1);
+    *frame.get_pixel(x as u32, y as u32)
+}
+
+// Runs `quad` for every pixel, with the pixel and its eight neighbours (in reading
+// order, so the pixel itself is [4]), and fills in the 2x2 block it returns.
+fn upscale2x<F>(frame: &RgbaImage, quad: F) -> RgbaImage
+    where F: Fn(&[Rgba<u8>; 9]) -> [Rgba<u8>; 4]
+{
+    let mut out = RgbaImage::new(frame.width() * 2, frame.height() * 2);
+
+    for y in 0..frame.height() as i32 {
+        for x in 0..frame.width() as i32 {
+            let mut w = [Rgba([0; 4]); 9];
+            for (i, n) in w.iter_mut().enumerate() {
+                *n = pixel_at(frame, x + i as i32 % 3 - 1, y + i as i32 / 3 - 1);
+            }
+
+            let [top_left, top_right, bottom_left, bottom_right] = quad(&w);
+            let (x, y) = (x as u32 * 2, y as u32 * 2);
+            out.put_pixel(x, y, top_left);
+            out.put_pixel(x + 1, y, top_right);
+            out.put_pixel(x, y + 1, bottom_left);
+            out.put_pixel(x + 1, y + 1, bottom_right);
+        }
+    }
+
+    out
+}
+
+fn nearest2x(frame: &RgbaImage) -> RgbaImage {
+    upscale2x(frame, |w| [w[4]; 4])
+}
+
+// Where two neighbours on either side of a corner match each other, and not the
+// ones across from them, that corner takes their colour.
+fn scale2x(frame: &RgbaImage) -> RgbaImage {
+    upscale2x(frame, |w| {
+        let (b, d, e, f, h) = (w[1], w[3], w[4], w[5], w[7]);
+        if b == h || d == f {
+            return [e; 4];
+        }
+
+        [
+            if d == b { d } else { e },
+            if b == f { f } else { e },
+            if d == h { d } else { e },
+            if h == f { f } else { e },
+        ]
+    })
+}
+
+// Colours are compared the way hq2x compares them, by brightness and hue with
+// separate thresholds, so slightly different shades count as the same.
+fn yuv(p: Rgba<u8>) -> [i32; 3] {
+    let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+    [
+        (299 * r + 587 * g + 114 * b) / 1000,
+        (-169 * r - 331 * g + 500 * b) / 1000,
+        (500 * r - 419 * g - 81 * b) / 1000,
+    ]
+}
+
+fn differ(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    let (a, b) = (yuv(a), yuv(b));
+    (a[0] - b[0]).abs() > 48 || (a[1] - b[1]).abs() > 7 || (a[2] - b[2]).abs() > 6
+}
+
+fn mix(colors: &[(Rgba<u8>, u32)]) -> Rgba<u8> {
+    let total: u32 = colors.iter().map(|&(_, w)| w).sum();
+    let mut out = [0, 0, 0, 255];
+    for (i, channel) in out.iter_mut().enumerate().take(3) {
+        *channel = (colors.iter().map(|&(c, w)| c[i] as u32 * w).sum::<u32>() / total) as u8;
+    }
+    Rgba(out)
+}
+
+// One corner of a pixel, from the neighbours on either side of it and the one
+// diagonally across.
+fn hq2x_corner(e: Rgba<u8>, side_a: Rgba<u8>, side_b: Rgba<u8>, corner: Rgba<u8>) -> Rgba<u8> {
+    match (differ(e, side_a), differ(e, side_b)) {
+        // An edge runs diagonally across the corner.
+        (true, true) if !differ(side_a, side_b) => {
+            if differ(side_a, corner) {
+                mix(&[(e, 2), (side_a, 1), (side_b, 1)])
+            } else {
+                mix(&[(e, 2), (side_a, 3), (side_b, 3)])
+            }
+        },
+        // The corner of a shape, which stays sharp.
+        (true, true) => e,
+        (true, false) => mix(&[(e, 3), (side_a, 1)]),
+        (false, true) => mix(&[(e, 3), (side_b, 1)]),
+        (false, false) if differ(e, corner) => mix(&[(e, 3), (corner, 1)]),
+        (false, false) => e,
+    }
+}
+
+// A cut down hq2x. The real thing looks the neighbours' pattern up in a table of
+// 256 hand-tuned cases, but most of them come down to these few, applied to each
+// corner in turn, which is plenty for the game's flat colours.
+fn hq2x(frame: &RgbaImage) -> RgbaImage {
+    upscale2x(frame, |w| [
+        hq2x_corner(w[4], w[1], w[3], w[0]),
+        hq2x_corner(w[4], w[1], w[5], w[2]),
+        hq2x_corner(w[4], w[7], w[3], w[6]),
+        hq2x_corner(w[4], w[7], w[5], w[8]),
+    ])
+}
+
+// A box blur, across and then down, keeping a running total along each line.
+// Only the pixels brighter than `threshold` are kept, and the rest are blacked out.
+// Brightness goes by luminance, with the Rec. 601 weights.
+fn bright_pass(image: &RgbaImage, threshold: f32) -> RgbaImage {
+    let mut out = image.clone();
+    for p in out.pixels_mut() {
+        let luminance = (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0;
+        let keep = ((luminance - threshold) / (1.0 - threshold)).max(0.0);
+        scale_channels(p, [keep; 3]);
+    }
+
+    out
+}
+
+fn blur(image: &RgbaImage, radius: i32) -> RgbaImage {
+    let pass = |source: &RgbaImage, horizontal: bool| {
+        let (width, height) = (source.width() as i32, source.height() as i32);
+        let (length, lines) = if horizontal { (width, height) } else { (height, width) };
+        let at = |line: i32, i: i32| {
+            let i = i.clamp(0, length - 1);
+            if horizontal { source.get_pixel(i as u32, line as u32) } else { source.get_pixel(line as u32, i as u32) }
+        };
+
+        let mut out = RgbaImage::new(source.width(), source.height());
+        let count = (radius * 2 + 1) as u32;
+
+        for line in 0..lines {
+            let mut sum = [0u32; 3];
+            for i in -radius..=radius {
+                let p = at(line, i);
+                for c in 0..3 {
+                    sum[c] += p[c] as u32;
+                }
+            }
+
+            for i in 0..length {
+                let p = Rgba([(sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8, 255]);
+                if horizontal { out.put_pixel(i as u32, line as u32, p) } else { out.put_pixel(line as u32, i as u32, p) }
+
+                let (entering, leaving) = (at(line, i + radius + 1), at(line, i - radius));
+                for c in 0..3 {
+                    sum[c] = sum[c] + entering[c] as u32 - leaving[c] as u32;
+                }
+            }
+        }
+
+        out
+    };
+
+    pass(&pass(image, true), false)
+}
\ No newline at end of file
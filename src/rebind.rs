@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use piston_window::{
+    ButtonArgs, ButtonState, Button,
+    keyboard::Key,
+};
+
+use crate::{
+    TILE_SIZE,
+    game::Game,
+    bindings::*,
+    renderer::RenderBackend,
+    framebuffer::screen_width,
+    text::*,
+};
+
+// A screen for changing the keys, drawn over the game, which waits while it's up.
+//
+//  Esc        Open or close the screen.
+//  Up, Down   Pick an action.
+//  Return     Add a key to the action. The next key pressed is the one added, and
+//             it's taken away from whatever it did before. The keys the game keeps
+//             for itself, like this screen's Esc, can't be added.
+//  Delete     Take away all of the action's keys.
+//  D          Go back to the original keys.
+//
+// The screen's own keys always work, whatever the game's are, so there's no way to
+// get stuck in it. Changes are saved as soon as they're made (see bindings.rs).
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.85];
+const SELECTED_COLOR: [f32; 4] = [0.0, 0.3, 0.6, 1.0];
+
+// The action names are padded out to this many characters, to line the keys up.
+const NAME_WIDTH: usize = 10;
+
+#[derive(Debug)]
+pub struct RebindMenu {
+    bindings: KeyBindings,
+    // Where changes are saved to. Without anywhere, they last until the game's closed.
+    path: Option<PathBuf>,
+    open: bool,
+    selected: usize,
+    // Waiting for the key to add to the selected action.
+    capturing: bool,
+    // How the last save went.
+    message: String,
+}
+
+impl RebindMenu {
+    pub fn new(bindings: KeyBindings, path: Option<PathBuf>) -> RebindMenu {
+        RebindMenu {
+            bindings,
+            path,
+            open: false,
+            selected: 0,
+            capturing: false,
+            message: String::new(),
+        }
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Returns true if the screen took the button, in which case nothing else should
+    // see it. While it's up, it takes everything.
+    pub fn handle_button(&mut self, args: ButtonArgs) -> bool {
+        let key = match args {
+            ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(key), .. } => key,
+            _ => return self.open,
+        };
+
+        if !self.open {
+            if key == Key::Escape {
+                self.open = true;
+                self.message.clear();
+            }
+            return self.open;
+        }
+
+        let action = Action::ALL[self.selected];
+        if self.capturing {
+            self.capturing = false;
+            if key != Key::Escape {
+                match self.bindings.bind(action, key) {
+                    Ok(()) => self.save(),
+                    Err(e) => self.message = e.to_string(),
+                }
+            }
+            return true;
+        }
+
+        match key {
+            Key::Escape => self.open = false,
+            Key::Up => self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len(),
+            Key::Down => self.selected = (self.selected + 1) % Action::ALL.len(),
+            Key::Return => self.capturing = true,
+            Key::Delete => {
+                self.bindings.clear(action);
+                self.save();
+            },
+            Key::D => {
+                self.bindings = KeyBindings::default();
+                self.save();
+            },
+            _ => (),
+        }
+
+        true
+    }
+
+    fn save(&mut self) {
+        self.message = match &self.path {
+            Some(path) => match self.bindings.save_file(path) {
+                Ok(()) => "Saved".to_owned(),
+                Err(e) => format!("Couldn't save: {}", e),
+            },
+            None => "Nowhere to save to, so they're only kept until the game's closed".to_owned(),
+        };
+    }
+
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        if !self.open {
+            return;
+        }
+
+        let width = screen_width(game);
+        let text = Text::new().wrap(width - 8);
+
+        let mut lines = vec!["Keys".to_owned()];
+        for (i, &action) in Action::ALL.iter().enumerate() {
+            let keys = if self.capturing && i == self.selected {
+                "Press a key...".to_owned()
+            } else {
+                let names: Vec<&str> = self.bindings.keys(action).iter().map(|&k| key_name(k)).collect();
+                names.join(" ")
+            };
+            lines.push(format!("{:<width$}{}", action.name(), keys, width = NAME_WIDTH));
+        }
+        lines.push(String::new());
+        if self.capturing {
+            lines.push("Esc cancels".to_owned());
+        } else {
+            lines.push("Return adds a key, Delete clears, D for the original keys, Esc closes".to_owned());
+        }
+        if !self.message.is_empty() {
+            lines.push(self.message.clone());
+        }
+
+        let body = lines.join("\n");
+        let (_, height) = text.size(&body);
+
+        let (x, y) = (2.0, TILE_SIZE as f64 + 2.0);
+        b.fill_rect(PANEL_COLOR, [x, y, (width - 4) as f64, (height + 4) as f64]);
+
+        // The actions start on the second line.
+        let selected_y = y + 2.0 + ((self.selected + 1) as u32 * LINE_HEIGHT) as f64;
+        b.fill_rect(SELECTED_COLOR, [x, selected_y - 1.0, (width - 4) as f64, (GLYPH_HEIGHT + 2) as f64]);
+
+        text.draw(b, &body, x + 2.0, y + 2.0);
+    }
+}
\ No newline at end of file
@@ -1,181 +1,431 @@
-use piston_window::*;
+use std::ops::Range;
+
+use image::RgbaImage;
+use piston_window::{
+    PistonWindow, Event, Context, G2d, Transformed, Rectangle, Line, Image, DrawState,
+    clear,
+    math::Matrix2d,
+};
 
 use crate::{
-    SCALE, TILE_SIZE,
+    Result, TILE_SIZE,
     game::*,
+    dave::COLLISION_PROBES,
     assets::*,
     tileset::*,
+    display::*,
+    camera::*,
+    text::*,
+    framebuffer::{Framebuffer, screen_width},
+    postprocess::{PostProcess, POST_PROCESS_SCALE},
+    accessibility::draw_outlines,
+    hud::HudLayout,
+    debug::DebugOverlay,
+    rebind::RebindMenu,
+    tas::Tas,
+    layer::LAYER_HEIGHT,
 };
-use crate::dave::HasJetpack;
+
+// The renderer only decides what goes where, and leaves the drawing to a backend.
+// The window uses Piston, and the framebuffer (see framebuffer.rs) draws on the
+// CPU, for screenshots and for running without a window.
+//
+// Everything is positioned in the game's own pixels, on its 200 pixel high screen
+// (320 wide, unless the view has been widened), and it's up to the backend to
+// scale that up (see display.rs).
+pub trait RenderBackend {
+    fn clear(&mut self, color: [f32; 4]);
+    fn draw_tile(&mut self, tile: TileId, x: f64, y: f64);
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]);
+    fn outline_rect(&mut self, color: [f32; 4], rect: [f64; 4]);
+    fn draw_line(&mut self, color: [f32; 4], from: [f64; 2], to: [f64; 2]);
+
+    // Draws the game's level tiles that don't animate, in the given columns, with
+    // the first of them at x. Backends can draw them all at once from a cached
+    // layer (see layer.rs), but otherwise they're drawn a tile at a time.
+    fn draw_static_tiles(&mut self, game: &Game, columns: Range<usize>, x: f64) {
+        draw_static_tiles(self, game.tiles(), columns, x);
+    }
+}
+
+fn draw_static_tiles<B: RenderBackend + ?Sized>(b: &mut B, tiles: &[TileId], columns: Range<usize>, x: f64) {
+    for (i, &tile) in tiles.iter().enumerate() {
+        let column = i % 100;
+        if columns.contains(&column) && !tile.is_animated() {
+            let tile_x = x + ((column - columns.start) as u32 * TILE_SIZE) as f64;
+            b.draw_tile(tile, tile_x, ((i / 100) as u32 + 1) as f64 * TILE_SIZE as f64);
+        }
+    }
+}
+
+pub struct PistonBackend<'a, 'b> {
+    gl: &'a mut G2d<'b>,
+    assets: &'a Assets,
+    layout: Layout,
+    transform: Matrix2d,
+    // Clips drawing to the game's screen, so nothing spills into the black bars.
+    draw_state: DrawState,
+}
+
+impl<'a, 'b> PistonBackend<'a, 'b> {
+    pub fn new(c: Context, gl: &'a mut G2d<'b>, assets: &'a Assets, display: &DisplaySettings, screen_width: u32) -> PistonBackend<'a, 'b> {
+        // Piston always gives draw_2d a viewport.
+        let viewport = c.viewport.expect("No viewport to draw to");
+        let [window_width, window_height] = viewport.window_size;
+        let layout = display.layout(screen_width, window_width, window_height);
+
+        // The scissor box is in real pixels, which aren't the same as window
+        // coordinates on high DPI displays.
+        let dpi = if window_width > 0.0 { viewport.draw_size[0] as f64 / window_width } else { 1.0 };
+        let scissor = [
+            (layout.x.max(0.0) * dpi) as u32,
+            (layout.y.max(0.0) * dpi) as u32,
+            (layout.width().min(window_width) * dpi) as u32,
+            (layout.height().min(window_height) * dpi) as u32,
+        ];
+
+        PistonBackend {
+            gl,
+            assets,
+            layout,
+            transform: c.transform.trans(layout.x, layout.y).scale(layout.scale_x, layout.scale_y),
+            draw_state: c.draw_state.scissor(scissor),
+        }
+    }
+}
+
+impl<'a, 'b> PistonBackend<'a, 'b> {
+    // Draws a whole frame that was put together elsewhere, at `scale` times the
+    // game's own size, over the top of everything, black bars included.
+    pub fn draw_frame(&mut self, frame: &RgbaImage, scale: u32) -> Result<()> {
+        let texture = self.assets.frame_texture(frame)?;
+        let scale = 1.0 / scale as f64;
+
+        clear([0.0, 0.0, 0.0, 1.0], self.gl);
+        Image::new().draw(&*texture, &self.draw_state, self.transform.scale(scale, scale), self.gl);
+        Ok(())
+    }
+}
+
+impl<'a, 'b> RenderBackend for PistonBackend<'a, 'b> {
+    // This clears the whole window, black bars and all.
+    fn clear(&mut self, color: [f32; 4]) {
+        clear(color, self.gl);
+    }
+
+    // A tile from a tile pack can be bigger than the original, but it's drawn over
+    // the same area.
+    fn draw_tile(&mut self, tile: TileId, x: f64, y: f64) {
+        let atlas = self.assets.atlas();
+        let [src_x, src_y, width, height] = atlas.rect(tile);
+        let (draw_width, draw_height) = self.assets.tile_images()[tile].dimensions();
+
+        Image::new()
+            .rect([0.0, 0.0, draw_width as f64, draw_height as f64])
+            .src_rect([src_x as f64, src_y as f64, width as f64, height as f64])
+            .draw(atlas.image(), &self.draw_state, self.transform.trans(x, y), self.gl);
+    }
+
+    fn fill_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        Rectangle::new(color).draw(rect, &self.draw_state, self.transform, self.gl);
+    }
+
+    // The border is kept a single window pixel wide, whatever the scale.
+    fn outline_rect(&mut self, color: [f32; 4], rect: [f64; 4]) {
+        Rectangle::new_border(color, 0.5 / self.layout.scale_x)
+            .draw(rect, &self.draw_state, self.transform, self.gl);
+    }
+
+    // The whole layer is one texture, and only the visible part of it is drawn.
+    fn draw_static_tiles(&mut self, game: &Game, columns: Range<usize>, x: f64) {
+        let texture = match self.assets.static_layer(game.tiles_stamp(), game.tiles()) {
+            Ok(texture) => texture,
+            Err(_) => return draw_static_tiles(self, game.tiles(), columns, x),
+        };
+
+        let width = (columns.len() as u32 * TILE_SIZE) as f64;
+        let scale = self.assets.tile_pack().scale() as f64;
+        let source = [
+            (columns.start as u32 * TILE_SIZE) as f64 * scale,
+            0.0,
+            width * scale,
+            LAYER_HEIGHT as f64 * scale,
+        ];
+        Image::new()
+            .rect([0.0, 0.0, width, LAYER_HEIGHT as f64])
+            .src_rect(source)
+            .draw(&*texture, &self.draw_state, self.transform.trans(x, TILE_SIZE as f64), self.gl);
+    }
+
+    // As thin as the outlines.
+    fn draw_line(&mut self, color: [f32; 4], from: [f64; 2], to: [f64; 2]) {
+        Line::new(color, 0.5 / self.layout.scale_x)
+            .draw([from[0], from[1], to[0], to[1]], &self.draw_state, self.transform, self.gl);
+    }
+}
+
+// What's drawn over the game, in order: the debug overlay, if it's switched on,
+// the TAS's panel, in tool-assisted mode, and the key bindings screen, if it's open.
+pub struct Overlays<'a> {
+    pub debug: &'a DebugOverlay,
+    pub tas: Option<&'a Tas>,
+    pub rebind: &'a RebindMenu,
+}
 
 #[derive(Debug)]
 pub struct Renderer {
     tick: usize,
+    display: DisplaySettings,
+    camera: Camera,
+    hud: HudLayout,
+    post_process: PostProcess,
+    high_contrast: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Renderer {
+        Renderer::new()
+    }
 }
 
 impl Renderer {
     pub fn new() -> Renderer {
-        Renderer { tick: 1 }
+        Renderer {
+            tick: 1,
+            display: Default::default(),
+            camera: Default::default(),
+            hud: Default::default(),
+            post_process: Default::default(),
+            high_contrast: false,
+        }
+    }
+
+    pub fn camera(mut self, mode: CameraMode) -> Renderer {
+        self.camera = Camera::new(mode);
+        self
     }
 
-    pub fn update(&mut self) {
+    pub fn hud(mut self, hud: HudLayout) -> Renderer {
+        self.hud = hud;
+        self
+    }
+
+    // Outlines the hazards, pickups and door (see accessibility.rs).
+    pub fn high_contrast(mut self, high_contrast: bool) -> Renderer {
+        self.high_contrast = high_contrast;
+        self
+    }
+
+    // Filters for the window (see postprocess.rs). They're left out of screenshots
+    // and recordings, which are always at the game's own resolution.
+    pub fn post_process(mut self, post_process: PostProcess) -> Renderer {
+        self.post_process = post_process;
+        self
+    }
+
+    // How the game is fitted into the window. Only the Piston backend uses it.
+    pub fn display(mut self, display: DisplaySettings) -> Renderer {
+        self.display = display;
+        self
+    }
+
+    pub fn update(&mut self, game: &Game) {
         self.tick += 1;
+        self.camera.update(game);
     }
 
-    pub fn render(&self, window: &mut PistonWindow, event: &Event, game: &Game, assets: &Assets) {
-        window.draw_2d(event, |c, gl, _| {
-            clear([0.0, 0.0, 0.0, 1.0], gl);
+    // For winding the camera back along with the game (see rewind.rs).
+    pub fn camera_state(&self) -> &Camera {
+        &self.camera
+    }
 
-            self.draw_world(c, gl, game, assets);
-            self.draw_dave(c, gl, game, assets);
-            self.draw_monsters(c, gl, game, assets);
-            if let Some(b) = game.dave().bullet() {
-                self.draw_bullet(c, gl, b, game, assets);
-            }
+    pub fn camera_state_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
 
-            if let Some(b) = game.monster_bullet() {
-                self.draw_bullet(c, gl, b, game, assets);
+    pub fn render(&self, window: &mut PistonWindow, event: &Event, game: &Game, assets: &Assets, overlays: &Overlays) -> Result<()> {
+        window.draw_2d(event, |c, gl, device| {
+            let mut backend = PistonBackend::new(c, gl, assets, &self.display, screen_width(game));
+            if !self.draw_post_processed(&mut backend, game, assets, |b| self.draw(b, game))? {
+                self.draw(&mut backend, game);
             }
+            overlays.debug.draw(&mut backend, game, assets.levels(), self.camera.view_x(game));
+            if let Some(tas) = overlays.tas {
+                tas.draw(&mut backend, game);
+            }
+            overlays.rebind.draw(&mut backend, game);
 
-            self.draw_ui(c, gl, game, assets);
-        });
+            assets.flush_textures(|encoder| encoder.flush(device));
+            Ok(())
+        }).unwrap_or(Ok(()))
     }
 
-    fn draw_ui(&self, c: Context, gl: &mut G2d, game: &Game, assets: &Assets) {
-        let tile_image = assets.get_tile(TileId::TILE_UI_SCORE);
-        let transform = c.transform.trans(1.0 * SCALE as f64, 0.0);
-        image(tile_image, transform, gl);
+    // Which pixel of the level is under the given point in the window, if any.
+    pub fn level_position(&self, game: &Game, window_size: [f64; 2], point: [f64; 2]) -> Option<Position<i16>> {
+        let layout = self.display.layout(screen_width(game), window_size[0], window_size[1]);
+        let x = (point[0] - layout.x) / layout.scale_x;
+        let y = (point[1] - layout.y) / layout.scale_y - TILE_SIZE as f64;
 
-        let tile_image = assets.get_tile(TileId::TILE_UI_LEVEL);
-        let transform = c.transform.trans(120.0 * SCALE as f64, 0.0);
-        image(tile_image, transform, gl);
+        let x = x + self.camera.view_x(game);
+        if x < 0.0 || x >= (100 * TILE_SIZE) as f64 || y < 0.0 || y >= (10 * TILE_SIZE) as f64 {
+            return None;
+        }
 
-        let tile_image = assets.get_tile(TileId::TILE_UI_DAVES);
-        let transform = c.transform.trans(200.0 * SCALE as f64, 0.0);
-        image(tile_image, transform, gl);
+        // Only the part of the level that's on the screen counts.
+        let screen_x = point[0] - layout.x;
+        if screen_x < 0.0 || screen_x >= layout.width() {
+            return None;
+        }
 
-        let score_digits = [1, 10, 100, 1000, 10000].into_iter()
-            .map(|d| TileId::get_digit_tile((game.score() / d) % 10));
+        Some(Position { x: x as i16, y: y as i16 })
+    }
 
-        for (digit, i) in score_digits.zip(0..) {
-            let tile = assets.get_tile(digit);
-            let transform = c.transform.trans(
-                ((96 - 8*i) * SCALE) as f64,
-                2.0
-            );
+    // The same as render, with the hitboxes drawn over the top, so it's clear why
+    // Dave died. The camera follows the live game, not the replay, so the replay
+    // is shown from where the camera was at the time, as the game kept it.
+    pub fn render_death_replay(&self, window: &mut PistonWindow, event: &Event, game: &Game, assets: &Assets, rebind: &RebindMenu) -> Result<()> {
+        window.draw_2d(event, |c, gl, device| {
+            let mut backend = PistonBackend::new(c, gl, assets, &self.display, screen_width(game));
+            if !self.draw_post_processed(&mut backend, game, assets, |b| self.draw_death_replay(b, game))? {
+                self.draw_death_replay(&mut backend, game);
+            }
+            rebind.draw(&mut backend, game);
 
-            image(tile, transform, gl);
-        }
+            assets.flush_textures(|encoder| encoder.flush(device));
+            Ok(())
+        }).unwrap_or(Ok(()))
+    }
 
-        let level_digits = [1, 10].into_iter()
-            .map(|d| TileId::get_digit_tile(((game.current_level().val() + 1) as u32 / d) % 10));
+    fn draw_death_replay<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        let view_x = match game.camera_x() {
+            Some(camera_x) => camera_x as f64,
+            None => Camera::classic_view_x(game),
+        };
+        self.draw_view(b, game, view_x);
+        self.draw_hitboxes(b, game, view_x);
 
-        for (digit, i) in level_digits.zip(0..) {
-            let tile = assets.get_tile(digit);
-            let transform = c.transform.trans(
-                ((178 - 8*i) * SCALE) as f64,
-                2.0
-            );
+        let middle = (screen_width(game) / 2) as f64;
+        Text::new()
+            .align(Align::Centre)
+            .draw(b, "Replay - Return to skip", middle, 20.0);
+    }
 
-            image(tile, transform, gl);
+    // With any filters on, the frame is drawn on the CPU with `draw`, filtered,
+    // and drawn to the window in one go. Returns false if it's been left for the
+    // caller to draw as usual.
+    fn draw_post_processed<F>(&self, b: &mut PistonBackend, game: &Game, assets: &Assets, draw: F) -> Result<bool>
+        where F: FnOnce(&mut Framebuffer)
+    {
+        if !self.post_process.is_enabled() {
+            return Ok(false);
         }
 
-        let lives_image = assets.get_tile(TileId::TILE_UI_DAVE);
-        for i in 0..game.lives().min(3) {
-            let transform = c.transform.trans(
-                ((255 + 16*i as u32) * SCALE) as f64,
-                2.0
-            );
-            image(lives_image, transform, gl);
-        }
+        let mut framebuffer = Framebuffer::new(assets.tile_images(), screen_width(game));
+        draw(&mut framebuffer);
+        let frame = self.post_process.apply(framebuffer.image());
+        b.draw_frame(&frame, POST_PROCESS_SCALE)
+            .map_err(|e| format!("Couldn't draw the filtered frame: {}", e))?;
+        Ok(true)
+    }
 
-        // Draw over the bottom of the level to fit in the UI.
-        let transform = c.transform.trans (
-            0.0 * SCALE as f64,
-            (150.0 + TILE_SIZE as f64) * SCALE as f64,
-        );
-        rectangle([0.0, 0.0, 0.0, 1.0], [0.0, 0.0, (320 * SCALE) as f64, 34.0 * SCALE as f64], transform, gl);
-
-        if game.has_trophy() {
-            let tile = assets.get_tile(TileId::TILE_UI_TROPHY);
-            let transform = c.transform.trans (
-                71.0 * SCALE as f64,
-                184.0 * SCALE as f64,
-            );
-            image(tile, transform, gl);
-        }
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        self.draw_view(b, game, self.camera.view_x(game));
+    }
 
-        if game.has_gun() {
-            let tile = assets.get_tile(TileId::TILE_UI_GUN);
-            let transform = c.transform.trans (
-                255.0 * SCALE as f64,
-                170.0 * SCALE as f64,
-            );
-            image(tile, transform, gl);
+    fn draw_view<B: RenderBackend>(&self, b: &mut B, game: &Game, view_x: f64) {
+        b.clear([0.0, 0.0, 0.0, 1.0]);
+
+        self.draw_world(b, game, view_x);
+        self.draw_dave(b, game, view_x);
+        self.draw_monsters(b, game, view_x);
+        if let Some(bullet) = game.dave().bullet() {
+            self.draw_bullet(b, bullet, view_x);
         }
 
-        if let HasJetpack::Yes(fuel) = game.has_jetpack() {
-            let tile = assets.get_tile(TileId::TILE_UI_JETPACK);
-            let transform = c.transform.trans (
-                1.0 * SCALE as f64,
-                170.0 * SCALE as f64,
-            );
-            image(tile, transform, gl);
-
-            let tile = assets.get_tile(TileId::TILE_UI_JETPACK_FUEL_BORDER);
-            let transform = c.transform.trans (
-                71.0 * SCALE as f64,
-                170.0 * SCALE as f64,
-            );
-            image(tile, transform, gl);
-
-            let segments = ((fuel as f64)/255.0 * 60.0).ceil() as u32;
-            let tile = assets.get_tile(TileId::TILE_UI_JETPACK_FUEL_BAR);
-            for i in 0..segments {
-                let transform = c.transform.trans (
-                    ((75 + i*2) * SCALE) as f64,
-                    174.0 * SCALE as f64,
-                );
-                image(tile, transform, gl);
-            }
+        if let Some(bullet) = game.monster_bullet() {
+            self.draw_bullet(b, bullet, view_x);
         }
 
-        let tile = assets.get_tile(TileId::TILE_UI_BORDER);
-        for i in 0..10 {
-            let x = (i * 32 * SCALE) as f64;
+        self.hud.draw(b, game);
+    }
 
-            let transform = c.transform.trans(x, 12.0 * SCALE as f64);
-            image(tile, transform, gl);
+    // Dave and the monsters kill each other when they're in the same grid cell,
+    // and monster bullets hit Dave the same way, so those cells are outlined. Each
+    // of Dave's collision probes is coloured by what it found in the level.
+    fn draw_hitboxes<B: RenderBackend>(&self, b: &mut B, game: &Game, view_x: f64) {
+        let to_screen = |x: i16, y: i16| Renderer::to_screen(view_x, Position { x, y });
 
-            let transform = c.transform.trans(x, 167.0 * SCALE as f64);
-            image(tile, transform, gl);
+        let outline_cell = |grid_x: i8, grid_y: i8, color, b: &mut B| {
+            let (x, y) = to_screen(grid_x as i16 * TILE_SIZE as i16, grid_y as i16 * TILE_SIZE as i16);
+            b.outline_rect(color, [x, y, TILE_SIZE as f64, TILE_SIZE as f64]);
+        };
+
+        for monster in game.monsters().iter().filter(|m| m.is_alive()) {
+            let pos = monster.position();
+            outline_cell(pos.x, pos.y, [1.0, 0.0, 0.0, 1.0], b);
+        }
+
+        if let Some(bullet) = game.monster_bullet() {
+            let grid_x = (bullet.position.x / TILE_SIZE as i16) as i8;
+            let grid_y = (bullet.position.y / TILE_SIZE as i16) as i8;
+            outline_cell(grid_x, grid_y, [1.0, 0.5, 0.0, 1.0], b);
+        }
+
+        let dave = game.dave();
+        outline_cell(dave.position.x, dave.position.y, [1.0, 1.0, 0.0, 1.0], b);
+
+        for &offset in COLLISION_PROBES.iter() {
+            let probe = dave.pixel_position + offset;
+            let color = match is_clear(game.tiles(), probe) {
+                CollisionType::Wall => [0.5, 0.5, 0.5, 1.0],
+                CollisionType::Hazard => [1.0, 0.0, 0.0, 1.0],
+                CollisionType::Pickup(..) => [1.0, 1.0, 0.0, 1.0],
+                CollisionType::Door => [0.0, 0.5, 1.0, 1.0],
+                CollisionType::Climbable => [0.0, 1.0, 0.0, 1.0],
+                _ => [1.0, 1.0, 1.0, 1.0],
+            };
+
+            let (x, y) = to_screen(probe.x, probe.y);
+            b.fill_rect(color, [x, y, 1.0, 1.0]);
         }
     }
 
-    fn draw_world(&self, c: Context, gl: &mut G2d, game: &Game, assets: &Assets) {
-        let level = assets.get_level(game.current_level());
-        let tiles = level.tiles().iter()
-            .enumerate()
-            .map(|(i, &t)| (i / 100, i % 100 - game.view_x() as usize, t));
+    fn draw_world<B: RenderBackend>(&self, b: &mut B, game: &Game, view_x: f64) {
+        // Only the columns on screen are drawn. The view can start part way through
+        // a tile, so the first and last of them may only be partly on screen.
+        let first_column = (view_x / TILE_SIZE as f64).floor() as usize;
+        let offset = view_x - (first_column as u32 * TILE_SIZE) as f64;
+        let last_column = ((view_x + screen_width(game) as f64) / TILE_SIZE as f64).ceil() as usize;
+        let columns = first_column..last_column.min(100);
 
-        for (y, x, tile) in tiles {
-            let transform = c.transform.trans(
-                (x as u32 * TILE_SIZE * SCALE) as f64,
-                ((y as u32 +1) * TILE_SIZE * SCALE) as f64
-            );
+        b.draw_static_tiles(game, columns.clone(), -offset);
+
+        let animated = game.tiles().iter()
+            .enumerate()
+            .filter(|(i, t)| columns.contains(&(i % 100)) && t.is_animated())
+            .map(|(i, &t)| (i / 100, i % 100 - first_column, t));
 
-            let tile_image = assets.get_tile(tile.get_frame(self.tick + x * TILE_SIZE as usize));
+        for (y, x, tile) in animated {
+            let tile = tile.get_frame(self.tick + x * TILE_SIZE as usize);
+            b.draw_tile(tile, (x as u32 * TILE_SIZE) as f64 - offset, ((y as u32 + 1) * TILE_SIZE) as f64);
+        }
 
-            image(tile_image, transform, gl);
+        if self.high_contrast {
+            draw_outlines(b, game, view_x, columns);
         }
     }
 
-    fn draw_dave(&self, c: Context, gl: &mut G2d, game: &Game, assets: &Assets) {
+    // Where something at the given level pixel position goes on the screen, with
+    // the view scrolled and the UI at the top.
+    pub fn to_screen(view_x: f64, pos: Position<i16>) -> (f64, f64) {
+        (
+            pos.x as f64 - view_x,
+            (pos.y + TILE_SIZE as i16) as f64,
+        )
+    }
+
+    fn draw_dave<B: RenderBackend>(&self, b: &mut B, game: &Game, view_x: f64) {
         let dave = game.dave();
-        let transform = c.transform.trans(
-            ((dave.pixel_position.x - (game.view_x() as i16 * TILE_SIZE as i16)) * SCALE as i16) as f64,
-            ((dave.pixel_position.y as u32 + TILE_SIZE) * SCALE) as f64,
-        );
 
         let tile_image = if !dave.is_alive() {
             TileId::TILE_MONSTER_DYING.get_frame(self.tick)
@@ -201,28 +451,18 @@ impl Renderer {
             }
         };
 
-        let tile_image = assets.get_tile(tile_image);
-        image(tile_image, transform, gl);
+        let (x, y) = Renderer::to_screen(view_x, dave.pixel_position);
+        b.draw_tile(tile_image, x, y);
     }
 
-    fn draw_monsters(&self, c: Context, gl: &mut G2d, game: &Game, assets: &Assets) {
+    fn draw_monsters<B: RenderBackend>(&self, b: &mut B, game: &Game, view_x: f64) {
         for monster in game.monsters().iter().filter(|m| m.is_not_dead()) {
-            let transform = c.transform.trans(
-                ((monster.pixel_position().x - (game.view_x() as i16 * TILE_SIZE as i16)) * SCALE as i16) as f64,
-                ((monster.pixel_position().y as u32 + TILE_SIZE) * SCALE) as f64,
-            );
-
-            let tile_image = assets.get_tile(monster.tile_id().get_frame(self.tick));
-            image(tile_image, transform, gl);
+            let (x, y) = Renderer::to_screen(view_x, monster.pixel_position());
+            b.draw_tile(monster.tile_id().get_frame(self.tick), x, y);
         }
     }
 
-    fn draw_bullet(&self, c: Context, gl: &mut G2d, bullet: &Bullet, game: &Game, assets: &Assets) {
-        let transform = c.transform.trans(
-            ((bullet.position.x - (game.view_x() as i16 * TILE_SIZE as i16)) * SCALE as i16) as f64,
-            ((bullet.position.y as u32 + TILE_SIZE) * SCALE) as f64,
-        );
-
+    fn draw_bullet<B: RenderBackend>(&self, b: &mut B, bullet: &Bullet, view_x: f64) {
         let tile_image = match (bullet.source, bullet.direction) {
             (BulletSource::Monster, Direction::Left) => TileId::TILE_ENEMY_BULLET_LEFT,
             (BulletSource::Monster, _) => TileId::TILE_ENEMY_BULLET_RIGHT,
@@ -230,7 +470,7 @@ impl Renderer {
             (BulletSource::Dave, _) => TileId::TILE_BULLET_RIGHT,
         };
 
-        let tile_image = assets.get_tile(tile_image.get_frame(self.tick));
-        image(tile_image, transform, gl);
+        let (x, y) = Renderer::to_screen(view_x, bullet.position);
+        b.draw_tile(tile_image.get_frame(self.tick), x, y);
     }
 }
\ No newline at end of file
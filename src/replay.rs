@@ -0,0 +1,153 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    Result,
+    level::LevelId,
+    input::Buttons,
+};
+
+// A replay is the level it starts on, and the buttons held for every update from
+// the start of that level. The game is deterministic, so that's all we need to
+// play it back.
+//
+// They're saved as text so they can be edited by hand. After the `level` line,
+// each line is a count and the buttons held for that many updates:
+//
+//  # Comments start with a hash.
+//  level 1
+//  30 R
+//  1 RU
+//  12 .
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub level: LevelId,
+    pub inputs: Vec<Buttons>,
+}
+
+impl Replay {
+    pub fn new(level: LevelId) -> Replay {
+        Replay {
+            level,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn save<W: Write>(&self, mut out: W) -> Result<()> {
+        // Level numbers are written as they're shown in the game, starting at 1.
+        writeln!(out, "level {}", self.level.val() + 1)?;
+
+        let mut inputs = self.inputs.iter().peekable();
+        while let Some(&buttons) = inputs.next() {
+            let mut count = 1;
+            while inputs.peek() == Some(&&buttons) {
+                inputs.next();
+                count += 1;
+            }
+
+            writeln!(out, "{} {}", count, buttons)?;
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(input: R) -> Result<Replay> {
+        let mut replay: Option<Replay> = None;
+
+        for (line_num, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next(), &mut replay) {
+                (Some("level"), Some(level), None, None) => {
+                    let level: usize = level.parse()?;
+                    let level = LevelId::new(level.wrapping_sub(1))?;
+                    replay = Some(Replay::new(level));
+                },
+                (Some(count), Some(buttons), None, Some(replay)) => {
+                    let count: usize = count.parse()?;
+                    let buttons: Buttons = buttons.parse()?;
+                    replay.inputs.extend(std::iter::repeat(buttons).take(count));
+                },
+                _ => Err(format!("Invalid replay data on line {}: {}", line_num + 1, line))?,
+            }
+        }
+
+        Ok(replay.ok_or("Replay is missing the level")?)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Replay> {
+        Replay::load(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_to_string(replay: &Replay) -> String {
+        let mut out = Vec::new();
+        replay.save(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn save_then_load() {
+        let mut replay = Replay::new(LevelId::new(2).unwrap());
+        replay.inputs.extend(std::iter::repeat(Buttons::RIGHT).take(30));
+        replay.inputs.push(Buttons::RIGHT | Buttons::JUMP);
+        replay.inputs.extend(std::iter::repeat(Buttons::NONE).take(12));
+        replay.inputs.push(Buttons::FIRE | Buttons::JETPACK | Buttons::DOWN | Buttons::LEFT);
+
+        let saved = save_to_string(&replay);
+        // Runs of the same buttons go on one line, and the level is counted from 1.
+        assert_eq!(saved, "level 3\n30 R\n1 RU\n12 .\n1 LDFJ\n");
+
+        let loaded = Replay::load(saved.as_bytes()).unwrap();
+        assert_eq!(loaded.level, replay.level);
+        assert_eq!(loaded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn load_skips_comments_and_blank_lines() {
+        let loaded = Replay::load("# A comment\n\nlevel 1\n  # Another\n2 r\n".as_bytes()).unwrap();
+        assert_eq!(loaded.level, LevelId::new(0).unwrap());
+        assert_eq!(loaded.inputs, vec![Buttons::RIGHT; 2]);
+    }
+
+    #[test]
+    fn load_rejects_malformed_files() {
+        let malformed = [
+            // Nothing at all, or inputs with no level.
+            "",
+            "30 R\n",
+            // Levels out of range, or more than one.
+            "level 0\n",
+            "level 11\n",
+            "level 1\nlevel 2\n",
+            // Counts and buttons that don't parse.
+            "level 1\nR 30\n",
+            "level 1\n-1 R\n",
+            "level 1\n30 RX\n",
+            // The wrong number of fields.
+            "level 1\n30\n",
+            "level 1\n30 R U\n",
+        ];
+
+        for file in malformed.iter() {
+            assert!(Replay::load(file.as_bytes()).is_err(), "{:?} should fail to load", file);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+
+use crate::{
+    UPDATES_PER_SECOND,
+    game::*,
+    level::*,
+    camera::Camera,
+};
+
+// Keeps a snapshot of the game from before each of the last few seconds of
+// updates, so play can be wound back. Handy for retrying a jump, and for
+// stepping back and forth over a collision that looks wrong.
+//
+// The camera is kept with each snapshot, so the smooth camera goes back with the
+// game, rather than easing over from wherever it had got to.
+
+pub const REWIND_SECONDS: usize = 10;
+
+struct Frame {
+    snapshot: Snapshot,
+    camera: Camera,
+}
+
+pub struct Rewind {
+    snapshots: VecDeque<Frame>,
+    capacity: usize,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Rewind::new(REWIND_SECONDS * UPDATES_PER_SECOND as usize)
+    }
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Called before every update. Once the buffer is full, the oldest snapshot
+    // is dropped to make room.
+    pub fn record(&mut self, game: &Game, camera: &Camera, levels: &Levels) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(Frame {
+            snapshot: game.snapshot(levels),
+            camera: camera.clone(),
+        });
+    }
+
+    // Puts the game back to how it was before the last update. Returns false
+    // once there's nothing left to go back to.
+    pub fn step_back(&mut self, game: &mut Game, camera: &mut Camera, levels: &Levels) -> bool {
+        match self.snapshots.pop_back() {
+            Some(frame) => {
+                game.restore(&frame.snapshot, levels);
+                *camera = frame.camera;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
\ No newline at end of file
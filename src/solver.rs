@@ -0,0 +1,338 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    game::*,
+    level::*,
+    dave::*,
+    monster::Monster,
+    input::{Input, Buttons},
+    replay::Replay,
+    tileset::TileId,
+    TILE_SIZE,
+};
+
+// Finds a sequence of inputs that takes the trophy and goes through the door of a
+// level, starting from a fresh game on that level. It's an A* search, where each
+// step is a single game update with one of a handful of button combinations.
+//
+// Rather than try to model the physics separately, games are stepped with
+// `Game::update`, so the solution is exactly what the game will do when the replay
+// is played back.
+//
+// A full game for every node would soon run to gigabytes, so the nodes only hold
+// the step that reached them. Every few updates along a path a snapshot is kept,
+// and a node's game is rebuilt when it's expanded by replaying the steps since the
+// last snapshot before it.
+//
+// Two games are only treated as the same state if everything that decides how the
+// game goes on from there is the same. That's all of Dave, the bullets, the view,
+// the pickups taken and the monsters, down to where they are along their paths.
+// Left out are the tick, score and lives, which only make a difference once Dave
+// has lost a life, and that's never part of a solution. So is what Dave touches,
+// which he checks again at the start of every update.
+//
+// The states seen are kept as 128 bit hashes of all that, rather than a copy of it,
+// which would be most of the memory used. The odds of two states sharing a hash are
+// far too small to matter, even over billions of them.
+
+// The heuristic is weighted, which makes the search much faster at the cost of
+// not always finding the shortest solution. We only care that there is one.
+const HEURISTIC_WEIGHT: u32 = 2;
+
+// Dave moves at most 2 pixels per update in any direction.
+const DAVE_SPEED: i32 = 2;
+
+// How many updates apart the snapshots along a path are. Expanding a node replays
+// up to this many updates, and the snapshots cost about a tenth of a full game each.
+const CHECKPOINT_INTERVAL: u32 = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub struct SolverStats {
+    pub nodes_expanded: usize,
+    pub states_seen: usize,
+}
+
+#[derive(Hash, Eq, PartialEq)]
+struct StateKey {
+    position: Position<i16>,
+    dave_state: Option<DaveState>,
+    has_jetpack: HasJetpack,
+    has_gun: bool,
+    bullet: Option<Bullet>,
+    view_x: i8,
+    scroll_x: i16,
+    has_trophy: bool,
+    collected: Vec<bool>,
+    monsters: Vec<Monster>,
+    monster_bullet: Option<Bullet>,
+}
+
+impl StateKey {
+    fn fingerprint(&self) -> u128 {
+        let mut low = DefaultHasher::new();
+        self.hash(&mut low);
+
+        // The same hash again, started off differently so that it's independent of
+        // the first.
+        let mut high = DefaultHasher::new();
+        high.write_u8(1);
+        self.hash(&mut high);
+
+        (high.finish() as u128) << 64 | low.finish() as u128
+    }
+}
+
+// How a node was reached, kept for every node so the replay can be built by walking
+// back through them.
+struct Step {
+    parent: usize,
+    action: Buttons,
+    snapshot: Option<Box<Snapshot>>,
+}
+
+struct Node {
+    cost: u32,
+    ticks: u32,
+    index: usize,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.cost == other.cost && self.ticks == other.ticks
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    // BinaryHeap is a max heap, so the ordering is reversed to get the cheapest
+    // node first. Ties go to the node furthest along.
+    fn cmp(&self, other: &Node) -> Ordering {
+        other.cost.cmp(&self.cost)
+            .then(self.ticks.cmp(&other.ticks))
+    }
+}
+
+pub struct Solver<'a> {
+    levels: &'a Levels,
+    max_nodes: usize,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(levels: &'a Levels) -> Solver<'a> {
+        Solver {
+            levels,
+            max_nodes: 50_000_000,
+        }
+    }
+
+    // Gives up after expanding this many nodes. Every state kept takes about 65
+    // bytes: its step in the history, its hash in the states seen, its place in the
+    // queue and its share of the snapshots. Expanding a node can keep up to 11 new
+    // states, but on the original levels it's only a little over one, which brings
+    // the default to nearly 4GB. Levels with more ways to go would need more.
+    pub fn max_nodes(mut self, max_nodes: usize) -> Solver<'a> {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn solve(&self, level: LevelId) -> (Option<Replay>, SolverStats) {
+        let start = Game::init_level(level, self.levels);
+
+        let pickups: Vec<usize> = start.tiles().iter()
+            .enumerate()
+            .filter(|(_, t)| t.is_pickup())
+            .map(|(i, _)| i)
+            .collect();
+
+        let trophies = tile_positions(start.tiles(), TileId::is_trophy);
+        let doors = tile_positions(start.tiles(), TileId::is_door);
+
+        let mut history = Vec::new();
+        let mut seen = HashSet::new();
+        let mut open = BinaryHeap::new();
+
+        seen.insert(state_key(&start, &pickups).fingerprint());
+        history.push(Step {
+            parent: usize::MAX,
+            action: Buttons::NONE,
+            snapshot: Some(Box::new(start.snapshot(self.levels))),
+        });
+        open.push(Node {
+            cost: 0,
+            ticks: 0,
+            index: 0,
+        });
+
+        let movement = [
+            Buttons::RIGHT,
+            Buttons::LEFT,
+            Buttons::JUMP,
+            Buttons::JUMP | Buttons::RIGHT,
+            Buttons::JUMP | Buttons::LEFT,
+            Buttons::DOWN,
+            Buttons::DOWN | Buttons::RIGHT,
+            Buttons::DOWN | Buttons::LEFT,
+            Buttons::NONE,
+        ];
+
+        let mut node_game = start.clone();
+        let mut nodes_expanded = 0;
+        while let Some(node) = open.pop() {
+            if nodes_expanded >= self.max_nodes {
+                break;
+            }
+            nodes_expanded += 1;
+
+            self.rebuild(&mut node_game, &history, node.index);
+
+            let actions = movement.iter()
+                .cloned()
+                .chain(if node_game.has_gun() { Some(Buttons::FIRE) } else { None })
+                .chain(if node_game.has_jetpack() != HasJetpack::No { Some(Buttons::JETPACK) } else { None });
+
+            for action in actions {
+                let mut game = node_game.clone();
+                self.step(&mut game, action);
+
+                let completed = game.current_level() != level
+                    || (game.quit() && game.dave().is_alive());
+
+                if completed {
+                    history.push(Step { parent: node.index, action, snapshot: None });
+                    let stats = SolverStats { nodes_expanded, states_seen: seen.len() };
+                    return (Some(build_replay(level, &history, history.len() - 1)), stats);
+                }
+
+                // Losing a life puts Dave back at the start, which is never useful.
+                if !game.dave().is_alive() || game.quit() {
+                    continue;
+                }
+
+                if !seen.insert(state_key(&game, &pickups).fingerprint()) {
+                    continue;
+                }
+
+                let ticks = node.ticks + 1;
+                let snapshot = if ticks % CHECKPOINT_INTERVAL == 0 {
+                    Some(Box::new(game.snapshot(self.levels)))
+                } else {
+                    None
+                };
+
+                history.push(Step { parent: node.index, action, snapshot });
+                let index = history.len() - 1;
+
+                let targets = if game.has_trophy() { &doors } else { &trophies };
+                open.push(Node {
+                    cost: ticks + HEURISTIC_WEIGHT * distance(game.dave().pixel_position, targets),
+                    ticks,
+                    index,
+                });
+            }
+        }
+
+        (None, SolverStats { nodes_expanded, states_seen: seen.len() })
+    }
+
+    fn step(&self, game: &mut Game, action: Buttons) {
+        game.input(&Input::from_buttons(action));
+        game.update(self.levels);
+        game.drain_sounds();
+    }
+
+    // Puts the game into the state of the node at `index`, from the last snapshot on
+    // the way to it.
+    fn rebuild(&self, game: &mut Game, history: &[Step], mut index: usize) {
+        let mut actions = Vec::new();
+        let snapshot = loop {
+            let step = &history[index];
+            match &step.snapshot {
+                Some(snapshot) => break snapshot,
+                None => {
+                    actions.push(step.action);
+                    index = step.parent;
+                },
+            }
+        };
+
+        game.restore(snapshot, self.levels);
+        for &action in actions.iter().rev() {
+            self.step(game, action);
+        }
+    }
+}
+
+fn tile_positions(tiles: &[TileId], filter: fn(TileId) -> bool) -> Vec<Position<i16>> {
+    tiles.iter()
+        .enumerate()
+        .filter(|(_, &t)| filter(t))
+        .map(|(i, _)| Position {
+            x: (i % 100) as i16 * TILE_SIZE as i16,
+            y: (i / 100) as i16 * TILE_SIZE as i16,
+        })
+        .collect()
+}
+
+// The least number of updates it could take Dave to reach the closest target.
+fn distance(from: Position<i16>, targets: &[Position<i16>]) -> u32 {
+    targets.iter()
+        .map(|t| {
+            let dx = (t.x as i32 - from.x as i32).abs();
+            let dy = (t.y as i32 - from.y as i32).abs();
+            (dx.max(dy) / DAVE_SPEED) as u32
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+fn state_key(game: &Game, pickups: &[usize]) -> StateKey {
+    let dave = game.dave();
+    let dave_state = match dave.state() {
+        state @ DaveState::Live {..} => {
+            let mut state = state.clone();
+            if let DaveState::Live {collision_point, can_climb, ..} = &mut state {
+                *collision_point = Default::default();
+                *can_climb = false;
+            }
+            Some(state)
+        },
+        _ => None,
+    };
+
+    StateKey {
+        position: dave.pixel_position,
+        dave_state,
+        has_jetpack: dave.has_jetpack,
+        has_gun: dave.has_gun,
+        bullet: dave.bullet().cloned(),
+        view_x: game.view_x(),
+        scroll_x: game.scroll_x(),
+        has_trophy: game.has_trophy(),
+        collected: pickups.iter().map(|&i| !game.tiles()[i].is_pickup()).collect(),
+        monsters: game.monsters().to_vec(),
+        monster_bullet: game.monster_bullet().cloned(),
+    }
+}
+
+fn build_replay(level: LevelId, history: &[Step], mut index: usize) -> Replay {
+    let mut inputs = Vec::new();
+    while index != 0 {
+        let step = &history[index];
+        inputs.push(step.action);
+        index = step.parent;
+    }
+
+    inputs.reverse();
+    Replay { level, inputs }
+}
\ No newline at end of file
@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+
+use piston_window::{
+    ButtonArgs, ButtonState, Button,
+    keyboard::Key,
+};
+
+use crate::{
+    Result, TILE_SIZE,
+    game::Game,
+    level::*,
+    input::Buttons,
+    replay::Replay,
+    bindings::{Action, KeyBindings},
+    renderer::RenderBackend,
+    framebuffer::screen_width,
+    text::*,
+};
+
+// Tool-assisted play. The game can be paused and stepped one update at a time,
+// saved to and loaded from numbered slots, and every input is logged so the run
+// can be exported, edited, and imported again. The tick, the inputs and the mode
+// are shown in the corner of the screen.
+//
+// The keys can be changed like the game's (see bindings.rs). By default:
+//
+//  P           Pause and resume.
+//  . (period)  Advance a single update while paused.
+//  F1-F9       Save the game to a slot.
+//  1-9         Load the game from a slot.
+//  M           Switch between recording new inputs and playing back the log.
+//  E           Export the input log.
+//  I           Import the input log, and play it back from the start.
+//
+// When playing back, the log is used for as long as it has inputs, and after that
+// the keyboard takes over again. When recording, the inputs from the current tick
+// onwards are replaced by the keyboard.
+
+const NUM_SLOTS: usize = 9;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Mode {
+    Record,
+    Playback,
+}
+
+struct SaveState {
+    game: Game,
+    tick: usize,
+}
+
+pub struct Tas {
+    log_path: PathBuf,
+    log: Replay,
+    // The number of updates since the start of the log.
+    tick: usize,
+    mode: Mode,
+    paused: bool,
+    advance: bool,
+    last_input: Buttons,
+    slots: Vec<Option<SaveState>>,
+}
+
+impl Tas {
+    // Starts a new run on the given level. If the log file exists, it's imported
+    // instead, and the game restarted on its level.
+    pub fn init<P: AsRef<Path>>(log_path: P, game: &mut Game, levels: &Levels) -> Result<Tas> {
+        let mut tas = Tas {
+            log_path: log_path.as_ref().to_owned(),
+            log: Replay::new(game.current_level()),
+            tick: 0,
+            mode: Mode::Record,
+            paused: true,
+            advance: false,
+            last_input: Buttons::NONE,
+            slots: (0..NUM_SLOTS).map(|_| None).collect(),
+        };
+
+        if tas.log_path.exists() {
+            tas.import(game, levels)?;
+        }
+
+        Ok(tas)
+    }
+
+    pub fn handle_button(&mut self, args: ButtonArgs, bindings: &KeyBindings, game: &mut Game, levels: &Levels) -> Result<()> {
+        let key = match args {
+            ButtonArgs { state: ButtonState::Press, button: Button::Keyboard(key), .. } => key,
+            _ => return Ok(()),
+        };
+
+        match bindings.action(key) {
+            Some(Action::Pause) => self.paused = !self.paused,
+            Some(Action::Step) => self.advance = true,
+            Some(Action::TasMode) => {
+                self.mode = match self.mode {
+                    Mode::Record => Mode::Playback,
+                    Mode::Playback => Mode::Record,
+                };
+            },
+            Some(Action::Export) => {
+                self.log.save_file(&self.log_path)?;
+                println!("Exported {} inputs to {}", self.log.inputs.len(), self.log_path.display());
+            },
+            Some(Action::Import) => self.import(game, levels)?,
+            Some(Action::SaveSlot) => {
+                if let Some(slot) = slot(bindings.keys(Action::SaveSlot), key) {
+                    self.slots[slot] = Some(SaveState {
+                        game: game.clone(),
+                        tick: self.tick,
+                    });
+                }
+            },
+            Some(Action::LoadSlot) => {
+                if let Some(state) = slot(bindings.keys(Action::LoadSlot), key).and_then(|slot| self.slots[slot].as_ref()) {
+                    *game = state.game.clone();
+                    self.tick = state.tick;
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    // Called every update, with the buttons currently held. Returns the buttons to
+    // update the game with, or None if the game shouldn't be updated.
+    pub fn next_input(&mut self, held: Buttons) -> Option<Buttons> {
+        self.last_input = held;
+
+        if self.paused && !self.advance {
+            return None;
+        }
+        self.advance = false;
+
+        let buttons = match (self.mode, self.log.inputs.get(self.tick)) {
+            (Mode::Playback, Some(&logged)) => logged,
+            _ => {
+                self.log.inputs.truncate(self.tick);
+                self.log.inputs.push(held);
+                held
+            }
+        };
+
+        self.last_input = buttons;
+        self.tick += 1;
+        Some(buttons)
+    }
+
+    pub fn title(&self) -> String {
+        format!("Dangerous Dave [TAS] tick {} / {} | {} ({:06b}) | {}{}",
+            self.tick,
+            self.log.inputs.len(),
+            self.last_input,
+            self.last_input.bits(),
+            match self.mode {
+                Mode::Record => "recording",
+                Mode::Playback => "playback",
+            },
+            if self.paused { " | paused" } else { "" },
+        )
+    }
+
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, game: &Game) {
+        let lines = [
+            format!("tick {} / {}", self.tick, self.log.inputs.len()),
+            format!("{} ({:06b})", self.last_input, self.last_input.bits()),
+            format!("{}{}",
+                match self.mode {
+                    Mode::Record => "recording",
+                    Mode::Playback => "playback",
+                },
+                if self.paused { ", paused" } else { "" },
+            ),
+        ];
+
+        let text = Text::new().align(Align::Right);
+        let body = lines.join("\n");
+        let (width, height) = text.size(&body);
+
+        let (x, y) = (screen_width(game) as f64 - 2.0, TILE_SIZE as f64 + 2.0);
+        b.fill_rect(PANEL_COLOR, [x - (width + 4) as f64, y, (width + 4) as f64, (height + 4) as f64]);
+        text.draw(b, &body, x - 2.0, y + 2.0);
+    }
+
+    fn import(&mut self, game: &mut Game, levels: &Levels) -> Result<()> {
+        self.log = Replay::load_file(&self.log_path)?;
+        *game = Game::init_level(self.log.level, levels).with_view_width(game.view_width());
+        self.tick = 0;
+        self.mode = Mode::Playback;
+        self.paused = true;
+
+        // Save states from another run don't make sense with this log.
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+
+        println!("Imported {} inputs from {}", self.log.inputs.len(), self.log_path.display());
+        Ok(())
+    }
+}
+
+// Which slot a key goes with, by where it is in the action's keys.
+fn slot(keys: &[Key], key: Key) -> Option<usize> {
+    keys.iter()
+        .position(|&k| k == key)
+        .filter(|&slot| slot < NUM_SLOTS)
+}
\ No newline at end of file
@@ -0,0 +1,307 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    queue,
+    cursor::MoveTo,
+    style::{Color, Print, SetForegroundColor, SetBackgroundColor, ResetColor},
+    terminal::{Clear, ClearType},
+    event::{KeyEvent, KeyEventKind, KeyCode, KeyModifiers},
+};
+use image::{RgbaImage, FilterType};
+
+use crate::{
+    Result, TILE_SIZE,
+    game::Game,
+    dave::HasJetpack,
+    tileset::{TileId, TileImages},
+    renderer::Renderer,
+    input::Buttons,
+    capture::render_frame,
+};
+
+// Draws the game in a terminal, for playing over SSH on machines with no display.
+//
+// The pixel view draws the screen as it would look in the window, two pixels to a
+// character, using the upper half block with the top pixel as the foreground colour
+// and the bottom one as the background. It needs a terminal with 24-bit colour, and
+// the tiles. The symbol view needs neither: each tile is two characters, picked by
+// what the tile does rather than what it looks like.
+//
+//  ##  Wall        ^^  Hazard      **  Pickup
+//  []  Door        HH  Climbable   @@  Dave
+//  MM  Monster     --  Bullet
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TerminalMode {
+    Pixels,
+    Symbols,
+}
+
+pub struct TerminalRenderer {
+    mode: TerminalMode,
+    renderer: Renderer,
+    tiles: Option<TileImages>,
+}
+
+impl TerminalRenderer {
+    // The tiles are only needed for the pixel view.
+    pub fn new(mode: TerminalMode, tiles: Option<TileImages>) -> Result<TerminalRenderer> {
+        if mode == TerminalMode::Pixels && tiles.is_none() {
+            Err("The pixel view needs the tiles")?;
+        }
+
+        Ok(TerminalRenderer {
+            mode,
+            renderer: Renderer::new(),
+            tiles,
+        })
+    }
+
+    // Called after every update.
+    pub fn update(&mut self, game: &Game) {
+        self.renderer.update(game);
+    }
+
+    // Draws a frame to fill the terminal, which is `size` columns by rows, keeping
+    // the bottom row for the status line.
+    pub fn draw<W: Write>(&self, out: &mut W, game: &Game, size: (u16, u16), status: &str) -> Result<()> {
+        let (columns, rows) = (size.0, size.1.saturating_sub(1));
+
+        match (self.mode, &self.tiles) {
+            (TerminalMode::Pixels, Some(tiles)) => {
+                self.draw_pixels(out, &render_frame(&self.renderer, game, tiles), columns, rows)?
+            },
+            _ => draw_symbols(out, game, columns, rows)?,
+        }
+
+        queue!(out, ResetColor, MoveTo(0, rows), Clear(ClearType::CurrentLine))?;
+        queue!(out, Print(truncate(status, columns)))?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn draw_pixels<W: Write>(&self, out: &mut W, frame: &RgbaImage, columns: u16, rows: u16) -> Result<()> {
+        // Each character is two pixels high, which keeps them roughly square.
+        let scale = (columns as f64 / frame.width() as f64)
+            .min((rows as u32 * 2) as f64 / frame.height() as f64);
+        let width = ((frame.width() as f64 * scale) as u32).max(1);
+        let height = ((frame.height() as f64 * scale) as u32).max(2);
+        let frame = image::imageops::resize(frame, width, height, FilterType::Nearest);
+
+        let color = |x, y| {
+            let p = frame.get_pixel(x, y);
+            Color::Rgb { r: p[0], g: p[1], b: p[2] }
+        };
+
+        // Colours are only sent when they change, which is most of the way to not
+        // sending them at all, with so many flat areas.
+        let mut last = None;
+        for row in 0..height / 2 {
+            queue!(out, MoveTo(0, row as u16))?;
+
+            for x in 0..width {
+                let colors = (color(x, row * 2), color(x, row * 2 + 1));
+                if last != Some(colors) {
+                    queue!(out, SetForegroundColor(colors.0), SetBackgroundColor(colors.1))?;
+                    last = Some(colors);
+                }
+                queue!(out, Print('\u{2580}'))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn truncate(s: &str, columns: u16) -> String {
+    s.chars().take(columns as usize).collect()
+}
+
+// The most specific kinds go first, so a tile that's more than one thing shows as
+// the one that matters most, rather than as a wall.
+fn tile_symbol(tile: TileId) -> (&'static str, Color) {
+    if tile.is_door() {
+        ("[]", Color::Cyan)
+    } else if tile.is_pickup() {
+        ("**", Color::Yellow)
+    } else if tile.is_hazard() {
+        ("^^", Color::Red)
+    } else if tile.is_climbable() {
+        ("HH", Color::Green)
+    } else if tile.is_collidable() {
+        ("##", Color::Grey)
+    } else {
+        ("  ", Color::Reset)
+    }
+}
+
+fn draw_symbols<W: Write>(out: &mut W, game: &Game, columns: u16, rows: u16) -> Result<()> {
+    let mut lines = Vec::new();
+
+    let mut hud = format!("SCORE {:05}  LEVEL {}  DAVES {}",
+        game.score(), game.current_level().val() + 1, game.lives());
+    if game.has_gun() {
+        hud += "  GUN";
+    }
+    if let HasJetpack::Yes(fuel) = game.has_jetpack() {
+        hud += &format!("  JETPACK {}%", fuel as u32 * 100 / 255);
+    }
+    if game.has_trophy() {
+        hud += "  GO THRU THE DOOR!";
+    }
+    lines.push(vec![(hud, Color::White)]);
+
+    // The level is laid out first, and then everything that moves goes over it.
+    let view_x = game.view_x() as usize;
+    let view_width = game.view_width() as usize;
+    let mut grid: Vec<Vec<(&str, Color)>> = (0..10)
+        .map(|y| (view_x..view_x + view_width)
+            .map(|x| tile_symbol(game.tiles()[y * 100 + x]))
+            .collect())
+        .collect();
+
+    let mut put = |pixel_x: i16, pixel_y: i16, symbol, color| {
+        let x = pixel_x / TILE_SIZE as i16 - view_x as i16;
+        let y = pixel_y / TILE_SIZE as i16;
+        if (0..view_width as i16).contains(&x) && (0..10).contains(&y) {
+            grid[y as usize][x as usize] = (symbol, color);
+        }
+    };
+
+    // Positions are rounded to the nearest cell, as they're drawn a cell at a time.
+    let half = TILE_SIZE as i16 / 2;
+    for monster in game.monsters().iter().filter(|m| m.is_alive()) {
+        let pos = monster.pixel_position();
+        put(pos.x + half, pos.y + half, "MM", Color::Magenta);
+    }
+    for bullet in game.dave().bullet().into_iter().chain(game.monster_bullet()) {
+        put(bullet.position.x, bullet.position.y, "--", Color::White);
+    }
+    let dave = game.dave().pixel_position;
+    put(dave.x + half, dave.y + half, "@@", Color::White);
+
+    for row in grid {
+        lines.push(row.into_iter().map(|(s, c)| (s.to_owned(), c)).collect());
+    }
+
+    let mut last = None;
+    for (y, line) in lines.iter().enumerate().take(rows as usize) {
+        queue!(out, MoveTo(0, y as u16), Clear(ClearType::CurrentLine))?;
+
+        let mut width = 0;
+        for &(ref text, color) in line {
+            if width >= columns {
+                break;
+            }
+
+            let text = truncate(text, columns - width);
+            width += text.chars().count() as u16;
+            if last != Some(color) {
+                queue!(out, SetForegroundColor(color))?;
+                last = Some(color);
+            }
+            queue!(out, Print(text))?;
+        }
+    }
+
+    queue!(out, ResetColor)?;
+    for y in lines.len()..rows as usize {
+        queue!(out, MoveTo(0, y as u16), Clear(ClearType::CurrentLine))?;
+    }
+
+    Ok(())
+}
+
+// Terminals send keys as they're typed, and most never say when they're let go,
+// so a key counts as held for a while after it's pressed, long enough to last until
+// the terminal starts repeating it. Terminals that can report releases (see
+// crossterm's keyboard enhancement flags) get the real thing.
+//
+//  Arrows      Move, jump and climb
+//  Space / Z   Fire
+//  J / X       Jetpack
+//  Q / Esc     Quit
+const KEY_HOLD: Duration = Duration::from_millis(400);
+
+const KEY_BUTTONS: [(char, Buttons); 4] = [
+    (' ', Buttons::FIRE),
+    ('z', Buttons::FIRE),
+    ('j', Buttons::JETPACK),
+    ('x', Buttons::JETPACK),
+];
+
+pub struct TerminalInput {
+    reports_releases: bool,
+    // When each button stops being held, in the same order as the bits.
+    held_until: [Option<Instant>; 6],
+    quit: bool,
+}
+
+impl TerminalInput {
+    pub fn new(reports_releases: bool) -> TerminalInput {
+        TerminalInput {
+            reports_releases,
+            held_until: [None; 6],
+            quit: false,
+        }
+    }
+
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        let button = match key.code {
+            KeyCode::Right => Buttons::RIGHT,
+            KeyCode::Left => Buttons::LEFT,
+            KeyCode::Up => Buttons::JUMP,
+            KeyCode::Down => Buttons::DOWN,
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.quit = true;
+                return;
+            },
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+                return;
+            },
+            KeyCode::Char(c) => match KEY_BUTTONS.iter().find(|&&(k, _)| k == c.to_ascii_lowercase()) {
+                Some(&(_, button)) => button,
+                None => return,
+            },
+            _ => return,
+        };
+
+        let bit = button.bits().trailing_zeros() as usize;
+        self.held_until[bit] = match key.kind {
+            KeyEventKind::Release => None,
+            // Held until it's let go, or close enough to forever.
+            _ if self.reports_releases => Some(Instant::now() + Duration::from_secs(3600)),
+            _ => Some(Instant::now() + KEY_HOLD),
+        };
+    }
+
+    // The buttons held for the next update. The jetpack is a toggle, so it's only
+    // pressed for one update however long the key is held.
+    pub fn next_buttons(&mut self) -> Buttons {
+        let now = Instant::now();
+        let mut buttons = Buttons::NONE;
+
+        for (bit, held_until) in self.held_until.iter_mut().enumerate() {
+            if let Some(until) = *held_until {
+                if until > now {
+                    buttons = buttons | Buttons::from_bits(1 << bit).unwrap_or(Buttons::NONE);
+                } else {
+                    *held_until = None;
+                }
+            }
+        }
+
+        let jetpack = Buttons::JETPACK.bits().trailing_zeros() as usize;
+        self.held_until[jetpack] = None;
+
+        buttons
+    }
+}
\ No newline at end of file
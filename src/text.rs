@@ -0,0 +1,286 @@
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    renderer::RenderBackend,
+    tileset::TileId,
+};
+
+// Text drawn with a small bitmap font. The original only has tiles for the digits
+// and a few labels (SCORE, LEVEL and DAVES), and draws its other text from images,
+// so the font is bundled here instead, in the same chunky white as the HUD digits.
+//
+// Each glyph is turned into a tile image when the tiles are loaded (see tileset.rs),
+// so text is drawn with draw_tile like everything else, by any backend. Lower case
+// letters are drawn as capitals, and anything else missing from the font as a '?'.
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+// A pixel between letters, and two between lines.
+pub const ADVANCE: u32 = GLYPH_WIDTH + 1;
+pub const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+const GLYPH_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+pub(crate) const NUM_GLYPHS: usize = GLYPHS.len();
+
+#[rustfmt::skip]
+const GLYPHS: &[(char, [&str; 7])] = &[
+    (' ', [".....", ".....", ".....", ".....", ".....", ".....", "....."]),
+    ('!', ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."]),
+    ('"', [".#.#.", ".#.#.", ".....", ".....", ".....", ".....", "....."]),
+    ('#', [".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#."]),
+    ('%', ["##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##"]),
+    ('&', [".##..", "#..#.", "#.#..", ".#...", "#.#.#", "#..#.", ".##.#"]),
+    ('\'', ["..#..", "..#..", ".....", ".....", ".....", ".....", "....."]),
+    ('(', ["...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#."]),
+    (')', [".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#..."]),
+    ('*', [".....", "..#..", "#.#.#", ".###.", "#.#.#", "..#..", "....."]),
+    ('+', [".....", "..#..", "..#..", "#####", "..#..", "..#..", "....."]),
+    (',', [".....", ".....", ".....", ".....", ".##..", "..#..", ".#..."]),
+    ('-', [".....", ".....", ".....", "#####", ".....", ".....", "....."]),
+    ('.', [".....", ".....", ".....", ".....", ".....", ".##..", ".##.."]),
+    ('/', [".....", "....#", "...#.", "..#..", ".#...", "#....", "....."]),
+    ('0', [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."]),
+    ('1', ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('2', [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"]),
+    ('3', ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."]),
+    ('4', ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."]),
+    ('5', ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."]),
+    ('6', ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."]),
+    ('7', ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."]),
+    ('8', [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."]),
+    ('9', [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."]),
+    (':', [".....", ".##..", ".##..", ".....", ".##..", ".##..", "....."]),
+    (';', [".....", ".##..", ".##..", ".....", ".##..", "..#..", ".#..."]),
+    ('<', ["...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#."]),
+    ('=', [".....", ".....", "#####", ".....", "#####", ".....", "....."]),
+    ('>', [".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#..."]),
+    ('?', [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."]),
+    ('@', [".###.", "#...#", "....#", ".##.#", "#.#.#", "#.#.#", ".###."]),
+    ('A', [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('B', ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."]),
+    ('C', [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."]),
+    ('D', ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###.."]),
+    ('E', ["#####", "#....", "#....", "####.", "#....", "#....", "#####"]),
+    ('F', ["#####", "#....", "#....", "####.", "#....", "#....", "#...."]),
+    ('G', [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"]),
+    ('H', ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "#....", "#####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"]),
+    ('N', ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"]),
+    ('O', [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('P', ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."]),
+    ('Q', [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"]),
+    ('R', ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"]),
+    ('S', [".####", "#....", "#....", ".###.", "....#", "....#", "####."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."]),
+    ('V', ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."]),
+    ('X', ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."]),
+    ('Z', ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"]),
+    ('[', [".###.", ".#...", ".#...", ".#...", ".#...", ".#...", ".###."]),
+    (']', [".###.", "...#.", "...#.", "...#.", "...#.", "...#.", ".###."]),
+    ('_', [".....", ".....", ".....", ".....", ".....", ".....", "#####"]),
+    ('{', ["...##", "..#..", "..#..", ".#...", "..#..", "..#..", "...##"]),
+    ('}', ["##...", "..#..", "..#..", "...#.", "..#..", "..#..", "##..."]),
+];
+
+// The images for every glyph, in the order of their tile ids.
+pub fn glyph_images() -> Vec<RgbaImage> {
+    GLYPHS.iter()
+        .map(|(_, rows)| {
+            let mut image = RgbaImage::new(GLYPH_WIDTH, GLYPH_HEIGHT);
+            for (y, row) in rows.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c == '#' {
+                        image.put_pixel(x as u32, y as u32, Rgba(GLYPH_COLOR));
+                    }
+                }
+            }
+
+            image
+        })
+        .collect()
+}
+
+fn glyph_tile(c: char) -> TileId {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter()
+        .position(|&(g, _)| g == c)
+        .or_else(|| GLYPHS.iter().position(|&(g, _)| g == '?'))
+        .and_then(TileId::glyph)
+        .unwrap_or(TileId::TILE_BLANK)
+}
+
+pub fn text_width(line: &str) -> u32 {
+    match line.chars().count() as u32 {
+        0 => 0,
+        n => n * ADVANCE - 1,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Centre,
+    Right,
+}
+
+// How a piece of text is laid out. The position it's drawn at is the top of the
+// first line, and, depending on the alignment, the left edge, the middle or the
+// right edge of every line.
+#[derive(Debug, Copy, Clone)]
+pub struct Text {
+    align: Align,
+    wrap_width: Option<u32>,
+}
+
+impl Default for Text {
+    fn default() -> Text {
+        Text::new()
+    }
+}
+
+impl Text {
+    pub fn new() -> Text {
+        Text {
+            align: Align::Left,
+            wrap_width: None,
+        }
+    }
+
+    pub fn align(mut self, align: Align) -> Text {
+        self.align = align;
+        self
+    }
+
+    // Wraps lines at spaces so none of them are wider than this, in pixels. Words
+    // too long to fit on a line of their own are split.
+    pub fn wrap(mut self, width: u32) -> Text {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    pub fn lines(&self, text: &str) -> Vec<String> {
+        let max_chars = match self.wrap_width {
+            Some(width) => ((width + 1) / ADVANCE).max(1) as usize,
+            None => usize::MAX,
+        };
+
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+
+            for word in paragraph.split(' ') {
+                let line_len = line.chars().count();
+                let word_len = word.chars().count();
+
+                // Spaces with no room left on the line are dropped, rather than
+                // starting a blank one.
+                if word_len == 0 && line_len + 1 > max_chars {
+                    continue;
+                }
+                if line_len > 0 && line_len + 1 + word_len > max_chars {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+
+                let mut chars = word.chars().peekable();
+                while chars.peek().is_some() {
+                    let room = max_chars - line.chars().count();
+                    if room == 0 {
+                        lines.push(std::mem::take(&mut line));
+                        continue;
+                    }
+                    line.extend(chars.by_ref().take(room));
+                }
+            }
+
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    // The size of the text once it's laid out, in pixels.
+    pub fn size(&self, text: &str) -> (u32, u32) {
+        let lines = self.lines(text);
+        let width = lines.iter().map(|l| text_width(l)).max().unwrap_or(0);
+        let height = match lines.len() as u32 {
+            0 => 0,
+            n => n * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT),
+        };
+
+        (width, height)
+    }
+
+    pub fn draw<B: RenderBackend>(&self, b: &mut B, text: &str, x: f64, y: f64) {
+        for (line, i) in self.lines(text).iter().zip(0..) {
+            let width = text_width(line) as f64;
+            let left = match self.align {
+                Align::Left => x,
+                Align::Centre => x - (width / 2.0).floor(),
+                Align::Right => x - width,
+            };
+            let top = y + (i * LINE_HEIGHT) as f64;
+
+            for (c, j) in line.chars().zip(0..) {
+                if c != ' ' {
+                    b.draw_tile(glyph_tile(c), left + (j * ADVANCE) as f64, top);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Room for this many characters a line.
+    fn wrap(chars: u32) -> Text {
+        Text::new().wrap(chars * ADVANCE - 1)
+    }
+
+    #[test]
+    fn lines_wrap_at_spaces() {
+        assert_eq!(wrap(5).lines("go thru the door"), vec!["go", "thru", "the", "door"]);
+        assert_eq!(wrap(7).lines("go thru the door"), vec!["go thru", "the", "door"]);
+        assert_eq!(Text::new().lines("go thru the door"), vec!["go thru the door"]);
+    }
+
+    #[test]
+    fn lines_split_long_words() {
+        assert_eq!(wrap(3).lines("jetpack"), vec!["jet", "pac", "k"]);
+        assert_eq!(wrap(3).lines("a jetpack"), vec!["a", "jet", "pac", "k"]);
+        assert_eq!(wrap(4).lines("gun trophy"), vec!["gun", "trop", "hy"]);
+        // However narrow, there's room for a character a line.
+        assert_eq!(Text::new().wrap(0).lines("dave"), vec!["d", "a", "v", "e"]);
+    }
+
+    #[test]
+    fn lines_keep_newlines() {
+        assert_eq!(Text::new().lines("level\n\n3"), vec!["level", "", "3"]);
+        assert_eq!(Text::new().lines("done\n"), vec!["done", ""]);
+        assert_eq!(wrap(4).lines("good work\ndave"), vec!["good", "work", "dave"]);
+    }
+
+    #[test]
+    fn lines_of_spaces() {
+        assert_eq!(Text::new().lines(""), vec![""]);
+        assert_eq!(Text::new().lines("   "), vec![""]);
+        assert_eq!(wrap(2).lines("      "), vec![""]);
+        // Spaces inside a line are kept, and the ones at the end of a full line go.
+        assert_eq!(Text::new().lines("a  b"), vec!["a  b"]);
+        assert_eq!(wrap(2).lines("ab   "), vec!["ab"]);
+        assert_eq!(wrap(2).lines("ab  cd"), vec!["ab", "cd"]);
+        assert_eq!(Text::new().size("   "), (0, GLYPH_HEIGHT));
+    }
+}
\ No newline at end of file
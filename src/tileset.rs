@@ -1,22 +1,28 @@
 use std::{
+    borrow::Cow,
     fmt::Write,
     ops::Index,
+    path::Path,
 };
 
-use image::{FilterType, Rgba, RgbaImage};
-use piston_window::{Texture, TextureSettings, G2dTextureContext, G2dTexture};
+use image::{Rgba, RgbaImage, FilterType};
+use piston_window::{Texture, TextureSettings, Filter, G2dTextureContext, G2dTexture};
 
-use crate::{SCALE, Result};
+use crate::{Result, text};
 
 const NUM_TILES: u8 = 159;
 
 // See the level.rs file comment for the reason behind this data structure.
 
+// The tiles as plain images, for rendering on the CPU. The window draws them from
+// an atlas made out of these (see TileAtlas, below).
 #[derive(Debug)]
-pub struct TileSet(Vec<G2dTexture>);
+pub struct TileSet<T>(Vec<T>);
 
-impl Index<TileId> for TileSet {
-    type Output = G2dTexture;
+pub type TileImages = TileSet<RgbaImage>;
+
+impl<T> Index<TileId> for TileSet<T> {
+    type Output = T;
     fn index(&self, idx: TileId) -> &Self::Output {
         // Because we're indexing through a type that can only be constructed
         // by going through validation, we can skip the bounds check here.
@@ -24,7 +30,23 @@ impl Index<TileId> for TileSet {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+impl TileImages {
+    // Changes the colour of every pixel, leaving the transparency alone.
+    pub fn recolor<F: FnMut([u8; 3]) -> [u8; 3]>(&mut self, mut f: F) {
+        for image in self.0.iter_mut() {
+            recolor_image(image, &mut f);
+        }
+    }
+}
+
+fn recolor_image<F: FnMut([u8; 3]) -> [u8; 3]>(image: &mut RgbaImage, f: &mut F) {
+    for p in image.pixels_mut() {
+        let [r, g, b] = f([p[0], p[1], p[2]]);
+        *p = Rgba([r, g, b, p[3]]);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct TileId(u8);
 
 impl TileId {
@@ -126,8 +148,14 @@ impl TileId {
     const TILE_ENEMY_BIG_DISK_LAST: u8 = 120;
 
     const TILE_UI_DIGIT_0: u8 = 148;
+
+    // The font's glyphs come after all of the game's own tiles (see text.rs).
+    const TILE_GLYPH_0: u8 = NUM_TILES;
 }
 
+// The glyphs' ids have to fit in a u8 too.
+const _: () = assert!(NUM_TILES as usize + text::NUM_GLYPHS <= 256);
+
 impl TileId {
     pub fn new(id: u8) -> Result<TileId> {
         if id < NUM_TILES {
@@ -137,6 +165,10 @@ impl TileId {
         }
     }
 
+    pub fn val(self) -> u8 {
+        self.0
+    }
+
     pub fn get_frame(self, tick: usize) -> TileId {
         let last_frame = match self.0 {
             TileId::TILE_FIRE_FIRST                 => TileId::TILE_FIRE_LAST,
@@ -170,6 +202,11 @@ impl TileId {
         TileId(self.0 + tile_offset as u8)
     }
 
+    // Whether the tile changes from frame to frame.
+    pub fn is_animated(self) -> bool {
+        self.get_frame(5) != self
+    }
+
     pub fn is_collidable(self) -> bool {
         match self.0 {
             1 | 3 | 5 | 15..=19 | 21..=24 | 29 | 30 => true,
@@ -191,6 +228,13 @@ impl TileId {
         }
     }
 
+    pub fn is_gem(self) -> bool {
+        match self.0 {
+            47..=52 => true,
+            _ => false,
+        }
+    }
+
     pub fn is_door(self) -> bool {
         self.0 == 2
     }
@@ -209,6 +253,15 @@ impl TileId {
         }
     }
 
+    // The tile for one of text.rs's glyphs, by its place in the font.
+    pub(crate) fn glyph(index: usize) -> Option<TileId> {
+        if index < text::NUM_GLYPHS {
+            Some(TileId(TileId::TILE_GLYPH_0 + index as u8))
+        } else {
+            None
+        }
+    }
+
     pub fn get_digit_tile(digit: u32) -> TileId {
         match digit {
             0..=9 => TileId(TileId::TILE_UI_DIGIT_0 + digit as u8),
@@ -241,7 +294,9 @@ impl TileId {
     }
 }
 
-pub fn load_tileset(mut context: G2dTextureContext) -> Result<TileSet> {
+// Loads the tiles as images, at their original size, with the transparent parts
+// masked out. Every renderer starts from these.
+pub fn load_tile_images() -> Result<TileImages> {
     let mut tiles = Vec::new();
 
     let mut name_buf = String::new();
@@ -271,22 +326,7 @@ pub fn load_tileset(mut context: G2dTextureContext) -> Result<TileSet> {
             }
         }
 
-        // Because we're doing a scaling here, and the rendering backend doesn't support it,
-        // we need to resize the tile image.
-        let image = image::imageops::resize(
-            &tile,
-            tile.width() * SCALE,
-            tile.height()* SCALE,
-            FilterType::Nearest,
-        );
-
-        let texture = Texture::from_image(
-            &mut context,
-            &image,
-            &TextureSettings::new(),
-        )?;
-
-        tiles.push(texture);
+        tiles.push(tile);
     }
 
     // The border image above and below the play area seem to be generated at runtime.
@@ -302,18 +342,183 @@ pub fn load_tileset(mut context: G2dTextureContext) -> Result<TileSet> {
         _ => Rgba([0, 0, 0, 255]),
     };
 
-    let mut image = RgbaImage::new(32*SCALE, 2*SCALE);
+    let mut image = RgbaImage::new(32, 2);
     for (x, _, d) in image.enumerate_pixels_mut() {
-        *d = pixel(x/SCALE);
+        *d = pixel(x);
     }
 
-    let texture = Texture::from_image(
-        &mut context,
-        &image,
-        &TextureSettings::new()
-    )?;
+    tiles.push(image);
 
-    tiles.push(texture);
+    tiles.extend(text::glyph_images());
 
     Ok(TileSet(tiles))
+}
+
+// Larger replacements for some or all of the tiles, from a folder of `tile<id>.png`
+// files named by tile id. Each has to be a whole multiple of the original's size,
+// like 32x32 or 64x64 for a 16x16 tile. The game still works in the original sizes,
+// and only the window draws the replacements, over the same area as the originals.
+// Tiles that aren't in the pack keep the originals. The PNGs' own alpha is used as
+// it is, rather than the masks the originals need.
+#[derive(Debug)]
+pub struct TilePack {
+    tiles: Vec<Option<RgbaImage>>,
+    scale: u32,
+}
+
+// An empty pack, which leaves every tile as it was.
+impl Default for TilePack {
+    fn default() -> TilePack {
+        TilePack {
+            tiles: Vec::new(),
+            scale: 1,
+        }
+    }
+}
+
+impl TilePack {
+    pub fn load<P: AsRef<Path>>(dir: P, originals: &TileImages) -> Result<TilePack> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            Err(format!("No tile pack at {}", dir.display()))?;
+        }
+
+        let mut pack = TilePack::default();
+        for (id, original) in originals.0.iter().enumerate() {
+            let path = dir.join(format!("tile{}.png", id));
+            if !path.exists() {
+                pack.tiles.push(None);
+                continue;
+            }
+
+            let image = image::open(&path)?.to_rgba();
+            let scale = image.width() / original.width();
+            if scale == 0 || image.width() != original.width() * scale || image.height() != original.height() * scale {
+                Err(format!("{} is {}x{}, which isn't a whole multiple of the original {}x{}",
+                    path.display(), image.width(), image.height(), original.width(), original.height()))?;
+            }
+
+            pack.scale = pack.scale.max(scale);
+            pack.tiles.push(Some(image));
+        }
+
+        Ok(pack)
+    }
+
+    // The largest of the replacements' scales, which is what the window's copy of
+    // the level is drawn at (see layer.rs).
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn recolor<F: FnMut([u8; 3]) -> [u8; 3]>(&mut self, mut f: F) {
+        for image in self.tiles.iter_mut().flatten() {
+            recolor_image(image, &mut f);
+        }
+    }
+
+    pub fn replaced(&self) -> usize {
+        self.tiles.iter().filter(|t| t.is_some()).count()
+    }
+
+    // The replacement for the tile, or the original if there isn't one.
+    pub fn image<'a>(&'a self, id: TileId, originals: &'a TileImages) -> &'a RgbaImage {
+        match self.tiles.get(id.0 as usize) {
+            Some(Some(image)) => image,
+            _ => &originals[id],
+        }
+    }
+
+    // The same, at the pack's scale, for drawing tiles in a grid.
+    pub fn scaled_image<'a>(&'a self, id: TileId, originals: &'a TileImages) -> Cow<'a, RgbaImage> {
+        let image = self.image(id, originals);
+        let original = &originals[id];
+        let (width, height) = (original.width() * self.scale, original.height() * self.scale);
+
+        if image.dimensions() == (width, height) {
+            Cow::Borrowed(image)
+        } else {
+            Cow::Owned(image::imageops::resize(image, width, height, FilterType::Nearest))
+        }
+    }
+}
+
+// Every tile packed into one image, so the window can draw them all from a single
+// texture, a sub-rectangle at a time. The tiles go in rows, in order, each with a
+// pixel of padding around it that repeats its edges, so a scaled tile never picks
+// up the edge of its neighbour.
+#[derive(Debug)]
+pub struct TileAtlas<T = G2dTexture> {
+    image: T,
+    rects: Vec<[u32; 4]>,
+}
+
+pub type AtlasImage = TileAtlas<RgbaImage>;
+
+const ATLAS_WIDTH: u32 = 256;
+
+impl<T> TileAtlas<T> {
+    pub fn image(&self) -> &T {
+        &self.image
+    }
+
+    // Where the tile is in the atlas, as x, y, width and height.
+    pub fn rect(&self, id: TileId) -> [u32; 4] {
+        self.rects[id.0 as usize]
+    }
+}
+
+// With a tile pack, the replacements go in instead of the originals.
+pub fn pack_atlas(images: &TileImages, pack: &TilePack) -> AtlasImage {
+    let tiles: Vec<&RgbaImage> = (0..images.0.len())
+        .map(|i| pack.image(TileId(i as u8), images))
+        .collect();
+
+    // Big replacements need a wider atlas, or it ends up far too tall to be a texture.
+    let area: u32 = tiles.iter().map(|t| (t.width() + 2) * (t.height() + 2)).sum();
+    let widest = tiles.iter().map(|t| t.width() + 2).max().unwrap_or(0);
+    let atlas_width = ATLAS_WIDTH.max(((area as f64).sqrt() as u32).next_power_of_two()).max(widest);
+
+    let mut rects = Vec::new();
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+
+    for tile in tiles.iter() {
+        let (width, height) = (tile.width() + 2, tile.height() + 2);
+        if x + width > atlas_width {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+
+        rects.push([x + 1, y + 1, tile.width(), tile.height()]);
+        x += width;
+        row_height = row_height.max(height);
+    }
+
+    let mut image = RgbaImage::new(atlas_width, y + row_height);
+    for (tile, &[x, y, width, height]) in tiles.iter().zip(rects.iter()) {
+        for py in 0..height + 2 {
+            for px in 0..width + 2 {
+                let from_x = (px.max(1) - 1).min(width - 1);
+                let from_y = (py.max(1) - 1).min(height - 1);
+                image.put_pixel(x - 1 + px, y - 1 + py, *tile.get_pixel(from_x, from_y));
+            }
+        }
+    }
+
+    TileAtlas { image, rects }
+}
+
+pub fn load_atlas(context: &mut G2dTextureContext, images: &TileImages, pack: &TilePack) -> Result<TileAtlas> {
+    let atlas = pack_atlas(images, pack);
+
+    // The tiles are kept at their original size, and scaled up as they're drawn,
+    // so they need to stay sharp rather than be smoothed.
+    let settings = TextureSettings::new().filter(Filter::Nearest);
+    let texture = Texture::from_image(context, &atlas.image, &settings)?;
+
+    Ok(TileAtlas {
+        image: texture,
+        rects: atlas.rects,
+    })
 }
\ No newline at end of file